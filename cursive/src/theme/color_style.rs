@@ -76,6 +76,11 @@ impl ColorStyle {
         Self::new(PaletteColor::View, PaletteColor::HighlightInactive)
     }
 
+    /// Alternate text with modified background, for unsaved changes.
+    pub fn modified() -> Self {
+        Self::new(PaletteColor::View, PaletteColor::Modified)
+    }
+
     /// Return the color pair that this style represents.
     pub fn resolve(&self, palette: &Palette) -> ColorPair {
         ColorPair {
@@ -39,6 +39,8 @@
 //!   Defaults to **red**.
 //! * **`HighlightInactive`**: used to highlight selected but inactive items.
 //!   Defaults to **blue**.
+//! * **`Modified`**: used to highlight content changed but not yet saved.
+//!   Defaults to **magenta**.
 //!
 //! A [`Palette`] then maps each of these to an actual [`Color`].
 //!
@@ -98,6 +100,9 @@
 //!   but inactive items.
 //!     * Its *background* color is `HighlightInactive`.
 //!     * Its *foreground* color is `View`.
+//! * **`ColorStyle::modified()`**: style used to print unsaved changes.
+//!     * Its *background* color is `Modified`.
+//!     * Its *foreground* color is `View`.
 //!
 //! Using one of these pairs when styling your application helps give it a
 //! coherent look.
@@ -116,6 +116,7 @@ impl Palette {
             "title_secondary" => self.basic[TitleSecondary] = color,
             "highlight" => self.basic[Highlight] = color,
             "highlight_inactive" => self.basic[HighlightInactive] = color,
+            "modified" => self.basic[Modified] = color,
             other => {
                 self.custom
                     .insert(other.to_string(), PaletteNode::Color(color));
@@ -146,6 +147,7 @@ impl Palette {
 /// * `TitleSecondary` => `Dark(Yellow)`
 /// * `Highlight` => `Dark(Red)`
 /// * `HighlightInactive` => `Dark(Blue)`
+/// * `Modified` => `Dark(Magenta)`
 impl Default for Palette {
     fn default() -> Palette {
         use self::PaletteColor::*;
@@ -164,6 +166,7 @@ impl Default for Palette {
                 TitleSecondary => Dark(Yellow),
                 Highlight => Dark(Red),
                 HighlightInactive => Dark(Blue),
+                Modified => Dark(Magenta),
             },
             custom: HashMap::new(),
         }
@@ -251,6 +254,8 @@ pub enum PaletteColor {
     Highlight,
     /// Color used for highlighting inactive text.
     HighlightInactive,
+    /// Color used for highlighting modified content.
+    Modified,
 }
 
 impl PaletteColor {
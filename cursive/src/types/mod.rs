@@ -2,12 +2,22 @@
 //! Vim-style commands to navigate and manipulate text.
 
 /// Represents the mode of the editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EditorMode {
     /// In normal mode, keys are used primarily for navigation and as macros for
     /// common functions.
     Normal,
     /// In insert mode, user can edit text content
     Insert,
+    /// In replace mode, typed bytes overwrite the content
+    Replace,
+    /// In command mode, user types an Ex command, such as `:w`, at the bottom
+    /// of the editor
+    Command,
+    /// In visual mode, motions extend a selection of bytes
+    Visual,
+    /// In visual line mode, motions extend a selection of whole rows
+    VisualLine,
 }
 
 impl ToString for EditorMode {
@@ -15,6 +25,10 @@ impl ToString for EditorMode {
         match self {
             EditorMode::Normal => "NORMAL".to_string(),
             EditorMode::Insert => "INSERT".to_string(),
+            EditorMode::Replace => "REPLACE".to_string(),
+            EditorMode::Command => "COMMAND".to_string(),
+            EditorMode::Visual => "VISUAL".to_string(),
+            EditorMode::VisualLine => "VISUAL LINE".to_string(),
         }
     }
 }
@@ -35,4 +49,33 @@ impl EditorMode {
             _ => false,
         }
     }
+
+    /// Tests if editor is in replace mode.
+    pub fn is_replace(&self) -> bool {
+        match self {
+            EditorMode::Replace => true,
+            _ => false,
+        }
+    }
+
+    /// Tests if typed keys edit the content, in insert or replace mode.
+    pub fn is_editing(&self) -> bool {
+        self.is_insert() || self.is_replace()
+    }
+
+    /// Tests if editor is in command mode.
+    pub fn is_command(&self) -> bool {
+        match self {
+            EditorMode::Command => true,
+            _ => false,
+        }
+    }
+
+    /// Tests if editor is in either visual mode.
+    pub fn is_visual(&self) -> bool {
+        match self {
+            EditorMode::Visual | EditorMode::VisualLine => true,
+            _ => false,
+        }
+    }
 }
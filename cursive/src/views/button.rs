@@ -169,6 +169,11 @@ impl View for Button {
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
+        // A disabled button can still be focused within a dialog.
+        if !self.enabled {
+            return EventResult::Ignored;
+        }
+
         // eprintln!("{:?}", event);
         // eprintln!("{:?}", self.req_size());
         let width = self.label.width();
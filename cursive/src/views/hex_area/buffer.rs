@@ -0,0 +1,873 @@
+//! Paged storage of the bytes edited by `HexArea`.
+//!
+//! Files are split into pages which are only read when first needed, so
+//! opening a file takes the same time whatever its size. Pages which were
+//! not modified can be dropped and read again later; modified pages stay in
+//! memory until they are saved.
+
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Index;
+use std::path::{Path, PathBuf};
+
+/// Number of bytes read from the file at once.
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// Number of unmodified pages kept in memory before they are dropped.
+const MAX_LOADED_PAGES: usize = 256;
+
+/// Bytes shown in place of those which could not be read from the file.
+static UNREADABLE: [u8; PAGE_SIZE] = [0; PAGE_SIZE];
+
+/// Run of bytes of the content.
+#[derive(Debug)]
+struct Page {
+    /// Position of the bytes of the page in the file, or `None` once they
+    /// were modified.
+    source: Option<u64>,
+
+    len: usize,
+
+    /// Bytes of the page, read from the file when first needed.
+    data: OnceCell<Vec<u8>>,
+}
+
+impl Page {
+    /// Creates a page holding modified bytes.
+    fn modified(bytes: Vec<u8>) -> Self {
+        Page {
+            source: None,
+            len: bytes.len(),
+            data: OnceCell::from(bytes),
+        }
+    }
+}
+
+/// Bytes edited by a `HexArea`, either held in memory or read from a file
+/// as needed.
+#[derive(Debug, Default)]
+pub struct Buffer {
+    /// File the unmodified pages are read from.
+    file: Option<File>,
+
+    /// Path of `file`.
+    path: Option<PathBuf>,
+
+    /// Invariant: no page is empty.
+    pages: Vec<Page>,
+
+    /// Offset of the first byte of each page.
+    starts: Vec<usize>,
+
+    len: usize,
+
+    /// Set when bytes were inserted or removed since the file was opened
+    /// or saved, moving pages away from their place in the file.
+    resized: bool,
+
+    /// Number of unmodified pages currently in memory.
+    loaded: Cell<usize>,
+
+    /// Last error met while reading the file, until `clear_read_error`.
+    read_error: RefCell<Option<String>>,
+}
+
+impl From<Vec<u8>> for Buffer {
+    /// Creates a buffer holding `bytes` in memory.
+    fn from(bytes: Vec<u8>) -> Self {
+        let pages = bytes
+            .chunks(PAGE_SIZE)
+            .map(|chunk| Page::modified(chunk.to_vec()))
+            .collect();
+        let mut buffer = Buffer {
+            len: bytes.len(),
+            pages,
+            ..Buffer::default()
+        };
+        buffer.update_starts(0);
+        buffer
+    }
+}
+
+impl Buffer {
+    /// Opens the file at `path`, without reading it yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+
+        let pages = (0..len)
+            .step_by(PAGE_SIZE)
+            .map(|start| Page {
+                source: Some(start as u64),
+                len: PAGE_SIZE.min(len - start),
+                data: OnceCell::new(),
+            })
+            .collect();
+        let mut buffer = Buffer {
+            file: Some(file),
+            path: Some(real_path(path)),
+            len,
+            pages,
+            ..Buffer::default()
+        };
+        buffer.update_starts(0);
+        Ok(buffer)
+    }
+
+    /// Number of bytes of the content.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the content holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the byte at `offset`, if any.
+    pub fn get(&self, offset: usize) -> Option<u8> {
+        if offset < self.len {
+            Some(self[offset])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bytes from `start` to `end`.
+    ///
+    /// Pages read from the file are kept in memory until `trim` drops them.
+    /// Bytes which cannot be read, such as past the end of a file truncated
+    /// by another program, are returned as zeros, see `read_error`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the range is out of bounds.
+    pub fn read(&self, start: usize, end: usize) -> Cow<'_, [u8]> {
+        self.read_pages(start, end, |index| Cow::Borrowed(self.load(index)))
+    }
+
+    /// Returns the bytes from `start` to `end`, like `read`, but without
+    /// keeping the pages read from the file.
+    ///
+    /// Meant for going through big parts of the content at once, such as
+    /// when searching.
+    pub(crate) fn read_uncached(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Cow<'_, [u8]> {
+        self.read_pages(start, end, |index| {
+            match self.pages[index].data.get() {
+                Some(data) => Cow::Borrowed(data),
+                None => match self.try_read_page(index) {
+                    Ok(data) => Cow::Owned(data),
+                    Err(why) => Cow::Borrowed(self.unreadable(index, why)),
+                },
+            }
+        })
+    }
+
+    /// Gathers the bytes from `start` to `end`, taking the bytes of each
+    /// page from `page`.
+    fn read_pages<'a, F>(
+        &'a self,
+        start: usize,
+        end: usize,
+        page: F,
+    ) -> Cow<'a, [u8]>
+    where
+        F: Fn(usize) -> Cow<'a, [u8]>,
+    {
+        assert!(start <= end && end <= self.len);
+        if start == end {
+            return Cow::Borrowed(&[]);
+        }
+
+        // Ranges within a single page are borrowed when possible.
+        let (first, offset) = self.locate(start);
+        if offset + (end - start) <= self.pages[first].len {
+            return match page(first) {
+                Cow::Borrowed(data) => {
+                    Cow::Borrowed(&data[offset..offset + end - start])
+                }
+                Cow::Owned(data) => {
+                    Cow::Owned(data[offset..offset + end - start].to_vec())
+                }
+            };
+        }
+
+        let mut bytes = Vec::with_capacity(end - start);
+        let mut index = first;
+        while bytes.len() < end - start {
+            let page_start = self.starts[index];
+            let from = (start + bytes.len()) - page_start;
+            let to = (end - page_start).min(self.pages[index].len);
+            bytes.extend_from_slice(&page(index)[from..to]);
+            index += 1;
+        }
+        Cow::Owned(bytes)
+    }
+
+    /// Returns the last error met while reading the file, if any.
+    ///
+    /// The bytes which could not be read are shown as zeros, but they can't
+    /// be modified.
+    pub fn read_error(&self) -> Option<String> {
+        self.read_error.borrow().clone()
+    }
+
+    /// Forgets the last error met while reading the file.
+    pub(crate) fn clear_read_error(&self) {
+        self.read_error.replace(None);
+    }
+
+    /// Writes the whole content to `writer`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (index, page) in self.pages.iter().enumerate() {
+            match page.data.get() {
+                Some(data) => writer.write_all(data)?,
+                None => writer.write_all(&self.try_read_page(index)?)?,
+            }
+        }
+        writer.flush()
+    }
+
+    /// Writes the content to the file at `path`, which then becomes the file
+    /// pages are read from.
+    ///
+    /// When `path` is the file the buffer was opened from and no bytes were
+    /// inserted or removed, only the modified pages are written. Otherwise
+    /// the content is written to a new file which then replaces the one at
+    /// `path`, unless that file could not be replaced without changing its
+    /// owner or breaking its hard links, in which case it is overwritten.
+    /// Symbolic links are followed, and stay in place.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = real_path(path.as_ref());
+        let is_source = self.path.as_ref() == Some(&path)
+            || self
+                .file
+                .as_ref()
+                .map_or(false, |file| is_file_at(file, &path));
+        if is_source && !self.resized {
+            return self.save_modified_pages(&path);
+        }
+
+        // Unmodified pages are still read from the old file while writing,
+        // so it is better not to overwrite it in place.
+        match create_replacement(&path) {
+            Some((file, temp)) => self.replace(file, &temp, &path)?,
+            None => self.rewrite(&path, is_source)?,
+        }
+
+        *self = Buffer::open(&path)?;
+        Ok(())
+    }
+
+    /// Writes the content to `file`, created by `create_replacement`, which
+    /// then takes the place of the file at `path`.
+    fn replace(
+        &self,
+        mut file: File,
+        temp: &Path,
+        path: &Path,
+    ) -> io::Result<()> {
+        let result = self
+            .write_to(&mut file)
+            .and_then(|_| fs::rename(temp, path));
+        if result.is_err() {
+            let _ = fs::remove_file(temp);
+        }
+        result
+    }
+
+    /// Overwrites the file at `path` with the content.
+    ///
+    /// When `is_source` is `true`, the unmodified pages are all read before
+    /// the file is overwritten, so this takes as much memory as the content.
+    fn rewrite(&self, path: &Path, is_source: bool) -> io::Result<()> {
+        let bytes = if is_source {
+            let mut bytes = Vec::with_capacity(self.len);
+            self.write_to(&mut bytes)?;
+            Some(bytes)
+        } else {
+            None
+        };
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        match bytes {
+            Some(bytes) => file.write_all(&bytes)?,
+            None => self.write_to(&mut file)?,
+        }
+        file.flush()
+    }
+
+    /// Writes the modified pages back to their place in the file.
+    fn save_modified_pages(&mut self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if page.source.is_some() {
+                continue;
+            }
+
+            let start = self.starts[index] as u64;
+            if let Some(data) = page.data.get() {
+                file.seek(SeekFrom::Start(start))?;
+                file.write_all(data)?;
+            }
+            page.source = Some(start);
+            self.loaded.set(self.loaded.get() + 1);
+        }
+        file.flush()
+    }
+
+    /// Inserts `bytes` before the byte at `offset`, or at the end if
+    /// `offset` is the length of the content.
+    ///
+    /// Fails without changing anything if the page the bytes go to can't be
+    /// read from the file.
+    pub(crate) fn insert(
+        &mut self,
+        offset: usize,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        assert!(offset <= self.len);
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let (index, offset) = if self.pages.is_empty() {
+            self.pages.push(Page::modified(Vec::new()));
+            self.starts.push(0);
+            (0, 0)
+        } else if offset == self.len {
+            let last = self.pages.len() - 1;
+            (last, self.pages[last].len)
+        } else {
+            self.locate(offset)
+        };
+
+        self.try_load(index)?;
+        let data = self.modify(index);
+        data.splice(offset..offset, bytes.iter().cloned());
+        let len = data.len();
+        self.pages[index].len = len;
+        self.len += bytes.len();
+        self.resized = true;
+
+        // Big pages are split so that edits stay cheap.
+        if len > 2 * PAGE_SIZE {
+            let data = self.pages[index].data.take().unwrap_or_default();
+            let pages = data
+                .chunks(PAGE_SIZE)
+                .map(|chunk| Page::modified(chunk.to_vec()));
+            self.pages.splice(index..=index, pages);
+        }
+        self.update_starts(index);
+        Ok(())
+    }
+
+    /// Removes the bytes from `start` to `end`.
+    ///
+    /// Fails without changing anything if a page which keeps some of its
+    /// bytes can't be read from the file.
+    pub(crate) fn remove(
+        &mut self,
+        start: usize,
+        end: usize,
+    ) -> io::Result<()> {
+        assert!(start <= end && end <= self.len);
+        if start == end {
+            return Ok(());
+        }
+
+        // Pages removed as a whole don't need to be read.
+        let (first, offset) = self.locate(start);
+        if offset > 0 || end - start < self.pages[first].len {
+            self.try_load(first)?;
+        }
+        let (last, offset) = self.locate(end - 1);
+        if offset + 1 < self.pages[last].len {
+            self.try_load(last)?;
+        }
+
+        let mut remaining = end - start;
+        while remaining > 0 {
+            let (index, offset) = self.locate(start);
+            let page_len = self.pages[index].len;
+            let count = remaining.min(page_len - offset);
+
+            // Whole pages are dropped without being read.
+            if count == page_len {
+                self.forget(index);
+                self.pages.remove(index);
+                self.starts.remove(index);
+            } else {
+                let data = self.modify(index);
+                data.drain(offset..offset + count);
+                self.pages[index].len -= count;
+            }
+
+            self.len -= count;
+            remaining -= count;
+            self.resized = true;
+            self.update_starts(index);
+        }
+        Ok(())
+    }
+
+    /// Overwrites the bytes from `offset` with `bytes`.
+    ///
+    /// Fails without changing anything if one of the pages they go to can't
+    /// be read from the file.
+    pub(crate) fn write(
+        &mut self,
+        offset: usize,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        assert!(offset + bytes.len() <= self.len);
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let (first, _) = self.locate(offset);
+        let (last, _) = self.locate(offset + bytes.len() - 1);
+        for index in first..=last {
+            self.try_load(index)?;
+        }
+
+        let mut written = 0;
+        while written < bytes.len() {
+            let (index, start) = self.locate(offset + written);
+            let data = self.modify(index);
+            let count = (bytes.len() - written).min(data.len() - start);
+            data[start..start + count]
+                .copy_from_slice(&bytes[written..written + count]);
+            written += count;
+        }
+        Ok(())
+    }
+
+    /// Drops the unmodified pages kept in memory if there are too many of
+    /// them.
+    pub(crate) fn trim(&mut self) {
+        if self.loaded.get() <= MAX_LOADED_PAGES {
+            return;
+        }
+
+        for page in &mut self.pages {
+            if page.source.is_some() {
+                page.data.take();
+            }
+        }
+        self.loaded.set(0);
+    }
+
+    /// Finds the page holding the byte at `offset`, and the position of
+    /// that byte within the page.
+    fn locate(&self, offset: usize) -> (usize, usize) {
+        assert!(offset < self.len);
+        let index = self.starts.partition_point(|&start| start <= offset) - 1;
+        (index, offset - self.starts[index])
+    }
+
+    /// Recomputes the start of the pages from `index` on.
+    fn update_starts(&mut self, index: usize) {
+        self.starts.truncate(index);
+        let mut start = match index {
+            0 => 0,
+            _ => self.starts[index - 1] + self.pages[index - 1].len,
+        };
+        for page in &self.pages[index..] {
+            self.starts.push(start);
+            start += page.len;
+        }
+    }
+
+    /// Returns the bytes of a page, which then counts as modified.
+    ///
+    /// The page must have been read with `try_load` first.
+    fn modify(&mut self, index: usize) -> &mut Vec<u8> {
+        self.forget(index);
+        let page = &mut self.pages[index];
+        page.source = None;
+        page.data.get_mut().expect("Page should be loaded")
+    }
+
+    /// Stops counting a page as an unmodified page in memory.
+    fn forget(&mut self, index: usize) {
+        let page = &self.pages[index];
+        if page.source.is_some() && page.data.get().is_some() {
+            self.loaded.set(self.loaded.get() - 1);
+        }
+    }
+
+    /// Returns the bytes of a page, reading them from the file if needed.
+    ///
+    /// Pages which cannot be read are not kept, so that reading them is
+    /// tried again later.
+    fn load(&self, index: usize) -> &[u8] {
+        match self.try_load(index) {
+            Ok(data) => data,
+            Err(why) => self.unreadable(index, why),
+        }
+    }
+
+    /// Returns the bytes of a page, reading them from the file if needed,
+    /// or the error met while reading them.
+    fn try_load(&self, index: usize) -> io::Result<&[u8]> {
+        let page = &self.pages[index];
+        if let Some(data) = page.data.get() {
+            return Ok(data);
+        }
+
+        let data = self.try_read_page(index)?;
+        self.loaded.set(self.loaded.get() + 1);
+        Ok(page.data.get_or_init(|| data))
+    }
+
+    /// Remembers why a page could not be read, and returns the bytes shown
+    /// instead.
+    fn unreadable(&self, index: usize, why: io::Error) -> &'static [u8] {
+        self.read_error.replace(Some(why.to_string()));
+        &UNREADABLE[..self.pages[index].len]
+    }
+
+    /// Reads the bytes of an unmodified page from the file.
+    fn try_read_page(&self, index: usize) -> io::Result<Vec<u8>> {
+        let page = &self.pages[index];
+        let (mut file, source) = match (&self.file, page.source) {
+            (Some(file), Some(source)) => (file, source),
+            _ => return Ok(page.data.get().cloned().unwrap_or_default()),
+        };
+
+        let mut data = vec![0; page.len];
+        file.seek(SeekFrom::Start(source))?;
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Path of the file `path` leads to, following symbolic links.
+///
+/// Paths which do not lead to a file yet are kept as they are.
+fn real_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Creates an empty file next to the one at `path`, with the same owner and
+/// permissions, meant to replace it.
+///
+/// Returns `None` if the file cannot be replaced that way, such as when the
+/// directory is not writable or when the file has other hard links.
+fn create_replacement(path: &Path) -> Option<(File, PathBuf)> {
+    let metadata = fs::metadata(path).ok();
+    if metadata
+        .as_ref()
+        .is_some_and(|metadata| link_count(metadata) > 1)
+    {
+        return None;
+    }
+
+    let name = path.file_name()?.to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    let file = File::create(&temp).ok()?;
+    let copied = match metadata {
+        Some(metadata) => copy_metadata(&temp, &metadata),
+        None => Ok(()),
+    };
+    if copied.is_err() {
+        let _ = fs::remove_file(&temp);
+        return None;
+    }
+    Some((file, temp))
+}
+
+/// Whether `file` is the file at `path`, possibly through a hard link.
+#[cfg(unix)]
+fn is_file_at(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_file_at(_: &File, _: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_: &Metadata) -> u64 {
+    1
+}
+
+/// Gives the file at `path` the owner and permissions in `metadata`.
+#[cfg(unix)]
+fn copy_metadata(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    use std::os::unix::fs::{chown, MetadataExt};
+    // Changing the owner may clear some permissions, so it goes first.
+    chown(path, Some(metadata.uid()), Some(metadata.gid()))?;
+    fs::set_permissions(path, metadata.permissions())
+}
+
+/// Gives the file at `path` the permissions in `metadata`.
+#[cfg(not(unix))]
+fn copy_metadata(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    fs::set_permissions(path, metadata.permissions())
+}
+
+impl Index<usize> for Buffer {
+    type Output = u8;
+
+    fn index(&self, offset: usize) -> &u8 {
+        let (index, offset) = self.locate(offset);
+        &self.load(index)[offset]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// File in the temporary directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, bytes: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "buffer-{}-{}",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, bytes).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Deterministic stream of pseudo-random numbers.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn bytes(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+            .collect()
+    }
+
+    /// Checks that `buffer` holds `model`, through every way of reading it.
+    fn check(buffer: &Buffer, model: &[u8]) {
+        assert_eq!(buffer.len(), model.len());
+        assert!(buffer.pages.iter().all(|page| page.len > 0));
+        let mut start = 0;
+        for (page, &page_start) in buffer.pages.iter().zip(&buffer.starts) {
+            assert_eq!(page_start, start);
+            start += page.len;
+        }
+        assert_eq!(start, model.len());
+
+        let mut written = Vec::new();
+        buffer.write_to(&mut written).unwrap();
+        assert_eq!(written, model);
+        assert_eq!(buffer.read(0, model.len()), model);
+        assert_eq!(buffer.read_error(), None);
+    }
+
+    #[test]
+    fn mixed_edits_match_a_vec() {
+        let mut model = bytes(5 * PAGE_SIZE + 123, 0);
+        let file = TempFile::new("mixed", &model);
+        let mut buffer = Buffer::open(&file.0).unwrap();
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for step in 0..300 {
+            let len = model.len();
+            match rng.below(5) {
+                0 => {
+                    let offset = rng.below(len + 1);
+                    // Sometimes more than two pages, so that it is split.
+                    let count = match rng.below(10) {
+                        0 => 2 * PAGE_SIZE + rng.below(PAGE_SIZE),
+                        _ => 1 + rng.below(300),
+                    };
+                    let inserted = bytes(count, step as u8);
+                    buffer.insert(offset, &inserted).unwrap();
+                    model.splice(offset..offset, inserted);
+                }
+                1 if len > 0 => {
+                    let start = rng.below(len);
+                    // Sometimes across several pages.
+                    let count = match rng.below(10) {
+                        0 => rng.below(2 * PAGE_SIZE),
+                        _ => rng.below(300),
+                    };
+                    let end = (start + count).min(len);
+                    buffer.remove(start, end).unwrap();
+                    model.drain(start..end);
+                }
+                2 if len > 0 => {
+                    let offset = rng.below(len);
+                    buffer.write(offset, &[step as u8]).unwrap();
+                    model[offset] = step as u8;
+                }
+                3 if len > 0 => {
+                    let start = rng.below(len);
+                    let end = (start + rng.below(3 * PAGE_SIZE)).min(len);
+                    assert_eq!(buffer.read(start, end), &model[start..end]);
+                    assert_eq!(buffer.get(start), Some(model[start]));
+                }
+                _ => buffer.trim(),
+            }
+
+            if step % 50 == 49 {
+                check(&buffer, &model);
+                buffer.save(&file.0).unwrap();
+                assert_eq!(fs::read(&file.0).unwrap(), model);
+                check(&buffer, &model);
+            }
+        }
+    }
+
+    #[test]
+    fn saving_without_resizing_writes_only_the_modified_pages() {
+        let mut model = bytes(3 * PAGE_SIZE + 10, 1);
+        let file = TempFile::new("in-place", &model);
+        let mut buffer = Buffer::open(&file.0).unwrap();
+
+        buffer.write(PAGE_SIZE + 5, &[0xAA]).unwrap();
+        model[PAGE_SIZE + 5] = 0xAA;
+        buffer.write(3 * PAGE_SIZE + 9, &[0xBB]).unwrap();
+        model[3 * PAGE_SIZE + 9] = 0xBB;
+        buffer.save(&file.0).unwrap();
+
+        assert_eq!(fs::read(&file.0).unwrap(), model);
+        assert!(buffer.pages.iter().all(|page| page.source.is_some()));
+        // Only the two modified pages were ever read.
+        assert_eq!(buffer.loaded.get(), 2);
+        check(&buffer, &model);
+    }
+
+    #[test]
+    fn saving_to_another_file_leaves_the_first_one() {
+        let original = bytes(2 * PAGE_SIZE, 2);
+        let file = TempFile::new("source", &original);
+        let other = TempFile::new("copy", &[]);
+        let mut buffer = Buffer::open(&file.0).unwrap();
+
+        buffer.remove(10, PAGE_SIZE + 10).unwrap();
+        buffer.insert(0, b"new").unwrap();
+        let mut model = original.clone();
+        model.drain(10..PAGE_SIZE + 10);
+        model.splice(0..0, b"new".iter().cloned());
+        buffer.save(&other.0).unwrap();
+
+        assert_eq!(fs::read(&file.0).unwrap(), original);
+        assert_eq!(fs::read(&other.0).unwrap(), model);
+        assert_eq!(buffer.path.as_ref(), Some(&real_path(&other.0)));
+        check(&buffer, &model);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn saving_through_a_hard_link_keeps_the_content() {
+        let mut model = bytes(3 * PAGE_SIZE, 5);
+        let file = TempFile::new("linked", &model);
+        let link = TempFile::new("link", &[]);
+        fs::remove_file(&link.0).unwrap();
+        fs::hard_link(&file.0, &link.0).unwrap();
+        let mut buffer = Buffer::open(&file.0).unwrap();
+
+        buffer.write(5, &[0xAA]).unwrap();
+        model[5] = 0xAA;
+        buffer.save(&link.0).unwrap();
+        assert_eq!(fs::read(&link.0).unwrap(), model);
+        check(&buffer, &model);
+
+        // Resized, so the file is overwritten as the link can't be replaced.
+        buffer.insert(PAGE_SIZE, b"new").unwrap();
+        model.splice(PAGE_SIZE..PAGE_SIZE, b"new".iter().cloned());
+        buffer.save(&file.0).unwrap();
+        assert_eq!(fs::read(&file.0).unwrap(), model);
+        assert_eq!(fs::read(&link.0).unwrap(), model);
+        check(&buffer, &model);
+    }
+
+    #[test]
+    fn trimming_drops_only_unmodified_pages() {
+        let mut model = bytes((MAX_LOADED_PAGES + 2) * PAGE_SIZE, 3);
+        let file = TempFile::new("trim", &model);
+        let mut buffer = Buffer::open(&file.0).unwrap();
+
+        buffer.write(0, &[0xCC]).unwrap();
+        model[0] = 0xCC;
+        assert_eq!(buffer.read(0, model.len()), model);
+        assert_eq!(buffer.loaded.get(), MAX_LOADED_PAGES + 1);
+
+        buffer.trim();
+        assert_eq!(buffer.loaded.get(), 0);
+        assert!(buffer.pages[0].data.get().is_some());
+        assert!(buffer.pages[1].data.get().is_none());
+        check(&buffer, &model);
+    }
+
+    #[test]
+    fn pages_which_cannot_be_read_are_not_modified() {
+        let mut model = bytes(3 * PAGE_SIZE, 4);
+        let file = TempFile::new("unreadable", &model);
+        let mut buffer = Buffer::open(&file.0).unwrap();
+
+        // Another program cuts the file short.
+        let truncated = OpenOptions::new().write(true).open(&file.0).unwrap();
+        truncated.set_len(PAGE_SIZE as u64).unwrap();
+        model.truncate(PAGE_SIZE);
+
+        let last = 2 * PAGE_SIZE + 10;
+        assert_eq!(buffer.read(last, last + 1), &[0][..]);
+        assert!(buffer.read_error().is_some());
+        assert!(buffer.write(last, &[0xAA]).is_err());
+        assert!(buffer.write(PAGE_SIZE - 1, &[0xAA, 0xBB]).is_err());
+        assert!(buffer.insert(last, b"new").is_err());
+        assert!(buffer.remove(10, PAGE_SIZE + 10).is_err());
+        assert_eq!(buffer.len(), 3 * PAGE_SIZE);
+        assert!(buffer.pages[1..].iter().all(|page| page.source.is_some()));
+        assert!(buffer.pages[1..]
+            .iter()
+            .all(|page| page.data.get().is_none()));
+
+        // The readable page can still be changed and saved, leaving the
+        // rest of the file alone.
+        buffer.write(PAGE_SIZE - 1, &[0xCC]).unwrap();
+        model[PAGE_SIZE - 1] = 0xCC;
+        assert_eq!(buffer.read(0, PAGE_SIZE)[PAGE_SIZE - 1], 0xCC);
+        assert_eq!(buffer.pages[0].data.get().map(Vec::len), Some(PAGE_SIZE));
+        buffer.save(&file.0).unwrap();
+        assert_eq!(fs::read(&file.0).unwrap(), model);
+    }
+}
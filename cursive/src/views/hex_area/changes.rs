@@ -0,0 +1,398 @@
+//! Tracking of the bytes modified in `HexArea` since the last save.
+
+use super::history::Edit;
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+
+/// Saved state of a run of changed bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Original {
+    /// This many bytes which did not exist in the saved content.
+    Inserted(usize),
+    /// Saved value of bytes which were overwritten.
+    Replaced(Vec<u8>),
+}
+
+impl Original {
+    /// Number of bytes of the run.
+    pub fn len(&self) -> usize {
+        match self {
+            Original::Inserted(len) => *len,
+            Original::Replaced(bytes) => bytes.len(),
+        }
+    }
+
+    /// Saved value of the byte at `position` in the run, or `None` if it
+    /// was inserted.
+    fn get(&self, position: usize) -> Option<u8> {
+        match self {
+            Original::Inserted(_) => None,
+            Original::Replaced(bytes) => Some(bytes[position]),
+        }
+    }
+
+    /// Part of the run from `start` to `end`.
+    fn slice(&self, start: usize, end: usize) -> Original {
+        match self {
+            Original::Inserted(_) => Original::Inserted(end - start),
+            Original::Replaced(bytes) => {
+                Original::Replaced(bytes[start..end].to_vec())
+            }
+        }
+    }
+
+    /// Shortens the run to `at` bytes, returning the rest.
+    fn split_off(&mut self, at: usize) -> Original {
+        match self {
+            Original::Inserted(len) => {
+                let rest = *len - at;
+                *len = at;
+                Original::Inserted(rest)
+            }
+            Original::Replaced(bytes) => {
+                Original::Replaced(bytes.split_off(at))
+            }
+        }
+    }
+}
+
+/// Runs of bytes changed since the content was last saved, by offset of
+/// their first byte, with their saved state.
+///
+/// Runs never overlap, but may follow each other.
+#[derive(Debug, Default)]
+pub struct Changes(BTreeMap<usize, Original>);
+
+impl Changes {
+    /// Creates an empty set of changes.
+    pub fn new() -> Self {
+        Changes::default()
+    }
+
+    /// Updates the changes after `edit` was applied to the content.
+    pub fn apply(&mut self, edit: &Edit) {
+        match edit {
+            Edit::Insert { offset, bytes } => {
+                self.split(*offset);
+                self.shift(*offset, |start| start + bytes.len());
+                self.0.insert(*offset, Original::Inserted(bytes.len()));
+            }
+            Edit::Remove { offset, bytes, .. } => {
+                let end = offset + bytes.len();
+                self.take(*offset, end);
+                self.shift(end, |start| start - bytes.len());
+            }
+            Edit::Replace { offset, old, new } => {
+                // Bytes already changed keep their saved state.
+                let mut states: Vec<Option<Option<u8>>> =
+                    old.iter().map(|&byte| Some(Some(byte))).collect();
+                for (start, run) in self.take(*offset, offset + old.len()) {
+                    for position in 0..run.len() {
+                        states[start - offset + position] =
+                            Some(run.get(position));
+                    }
+                }
+
+                // Typing the original value back cancels the change.
+                for (state, &byte) in states.iter_mut().zip(new) {
+                    if *state == Some(Some(byte)) {
+                        *state = None;
+                    }
+                }
+                self.insert_states(*offset, &states);
+            }
+        }
+    }
+
+    /// Puts back the state of the bytes removed by `edit`, once undoing it
+    /// inserted them again.
+    ///
+    /// Without this, the restored bytes would count as inserted, although
+    /// they may be in the saved content.
+    pub fn restore(&mut self, edit: &Edit) {
+        if let Edit::Remove {
+            offset,
+            bytes,
+            changed,
+        } = edit
+        {
+            self.take(*offset, offset + bytes.len());
+            self.0.extend(
+                changed
+                    .iter()
+                    .map(|(position, run)| (offset + position, run.clone())),
+            );
+        }
+    }
+
+    /// Changed runs between `start` and `end`, cut to that range, by
+    /// position from `start`.
+    pub fn between(&self, start: usize, end: usize) -> Vec<(usize, Original)> {
+        let first = self.run_at(start).map_or(start, |(first, _)| first);
+        self.0
+            .range(first..end)
+            .filter_map(|(&run_start, run)| {
+                let from = max(run_start, start);
+                let to = min(run_start + run.len(), end);
+                if from >= to {
+                    return None;
+                }
+                let run = run.slice(from - run_start, to - run_start);
+                Some((from - start, run))
+            })
+            .collect()
+    }
+
+    /// Forgets every change, making the current content the original one.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns `true` if the byte at `offset` was changed.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.run_at(offset).is_some()
+    }
+
+    /// Run holding the byte at `offset`, with the offset it starts at.
+    fn run_at(&self, offset: usize) -> Option<(usize, &Original)> {
+        self.0
+            .range(..=offset)
+            .next_back()
+            .filter(|(&start, run)| start + run.len() > offset)
+            .map(|(&start, run)| (start, run))
+    }
+
+    /// Returns `true` if a run of changed bytes starts at `offset`.
+    fn starts_region(&self, offset: usize) -> bool {
+        self.contains(offset) && (offset == 0 || !self.contains(offset - 1))
+    }
+
+    /// Range of the run of changed bytes containing `offset`, if any.
+    ///
+    /// Runs which follow each other count as one.
+    pub fn region_at(&self, offset: usize) -> Option<(usize, usize)> {
+        let (mut start, run) = self.run_at(offset)?;
+        let mut end = start + run.len();
+        while let Some((previous, _)) =
+            start.checked_sub(1).and_then(|last| self.run_at(last))
+        {
+            start = previous;
+        }
+        while let Some(run) = self.0.get(&end) {
+            end += run.len();
+        }
+
+        Some((start, end))
+    }
+
+    /// Start of the first run of changed bytes after `offset`.
+    pub fn next_region(&self, offset: usize) -> Option<usize> {
+        self.0
+            .range(offset + 1..)
+            .map(|(&start, _)| start)
+            .find(|&start| self.starts_region(start))
+    }
+
+    /// Start of the last run of changed bytes before `offset`.
+    pub fn previous_region(&self, offset: usize) -> Option<usize> {
+        self.0
+            .range(..offset)
+            .rev()
+            .map(|(&start, _)| start)
+            .find(|&start| self.starts_region(start))
+    }
+
+    /// Splits the run holding the byte at `offset`, if any, so that a run
+    /// starts there.
+    fn split(&mut self, offset: usize) {
+        let rest = match self.0.range_mut(..offset).next_back() {
+            Some((&start, run)) if start + run.len() > offset => {
+                run.split_off(offset - start)
+            }
+            _ => return,
+        };
+        self.0.insert(offset, rest);
+    }
+
+    /// Removes the runs between `start` and `end`, cutting those which go
+    /// past that range, and returns them.
+    fn take(&mut self, start: usize, end: usize) -> BTreeMap<usize, Original> {
+        self.split(start);
+        self.split(end);
+        let mut taken = self.0.split_off(&start);
+        let mut after = taken.split_off(&end);
+        self.0.append(&mut after);
+        taken
+    }
+
+    /// Moves the runs starting from `offset` on to `to(start)`.
+    fn shift<F: Fn(usize) -> usize>(&mut self, offset: usize, to: F) {
+        let moved = self.0.split_off(&offset);
+        self.0
+            .extend(moved.into_iter().map(|(start, run)| (to(start), run)));
+    }
+
+    /// Records the state of the bytes from `offset` on, grouping them into
+    /// runs: `None` for unchanged bytes, `Some(None)` for inserted ones and
+    /// `Some(Some(byte))` for bytes which were `byte` when saved.
+    fn insert_states(&mut self, offset: usize, states: &[Option<Option<u8>>]) {
+        let mut position = 0;
+        while position < states.len() {
+            let start = position;
+            let run = match states[start] {
+                None => {
+                    position += 1;
+                    continue;
+                }
+                Some(None) => {
+                    while states.get(position) == Some(&Some(None)) {
+                        position += 1;
+                    }
+                    Original::Inserted(position - start)
+                }
+                Some(Some(_)) => {
+                    let mut bytes = Vec::new();
+                    while let Some(Some(Some(byte))) = states.get(position) {
+                        bytes.push(*byte);
+                        position += 1;
+                    }
+                    Original::Replaced(bytes)
+                }
+            };
+            self.0.insert(offset + start, run);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(offset: usize, old: &[u8], new: &[u8]) -> Edit {
+        Edit::Replace {
+            offset,
+            old: old.to_vec(),
+            new: new.to_vec(),
+        }
+    }
+
+    #[test]
+    fn undoing_a_removal_restores_the_bytes_as_they_were() {
+        let mut changes = Changes::new();
+
+        // `r00` at offset 0, then `x` at offset 10
+        changes.apply(&replace(0, &[0x41], &[0x00]));
+        let remove = Edit::Remove {
+            offset: 10,
+            bytes: vec![0x4A],
+            changed: changes.between(10, 11),
+        };
+        changes.apply(&remove);
+
+        // `u`
+        changes.apply(&remove.inverse());
+        changes.restore(&remove);
+
+        assert_eq!(original(&changes, 10), None);
+        assert_eq!(original(&changes, 0), Some(Some(0x41)));
+        assert_eq!(changes.next_region(0), None);
+    }
+
+    #[test]
+    fn undoing_a_removal_restores_changed_bytes() {
+        let mut changes = Changes::new();
+        changes.apply(&replace(3, &[0x10], &[0x20]));
+        changes.apply(&Edit::Insert {
+            offset: 5,
+            bytes: vec![0xFF],
+        });
+
+        let remove = Edit::Remove {
+            offset: 2,
+            bytes: vec![0; 5],
+            changed: changes.between(2, 7),
+        };
+        assert_eq!(
+            remove_changed(&remove),
+            &[
+                (1, Original::Replaced(vec![0x10])),
+                (3, Original::Inserted(1))
+            ]
+        );
+        changes.apply(&remove);
+        assert_eq!(changes.region_at(2), None);
+
+        changes.apply(&remove.inverse());
+        changes.restore(&remove);
+        assert_eq!(original(&changes, 2), None);
+        assert_eq!(original(&changes, 3), Some(Some(0x10)));
+        assert_eq!(original(&changes, 4), None);
+        assert_eq!(original(&changes, 5), Some(None));
+        assert_eq!(original(&changes, 6), None);
+    }
+
+    #[test]
+    fn big_edits_are_kept_as_runs() {
+        let mut changes = Changes::new();
+        let len = 1 << 20;
+        changes.apply(&replace(0, &vec![0; len], &vec![1; len]));
+        changes.apply(&Edit::Insert {
+            offset: len / 2,
+            bytes: vec![2; len],
+        });
+        assert_eq!(changes.0.len(), 3);
+        assert_eq!(changes.region_at(len), Some((0, 2 * len)));
+
+        changes.apply(&Edit::Remove {
+            offset: 10,
+            bytes: vec![0; len],
+            changed: Vec::new(),
+        });
+        assert_eq!(changes.0.len(), 3);
+        assert_eq!(original(&changes, 9), Some(Some(0)));
+        assert_eq!(original(&changes, 10), Some(None));
+        assert_eq!(original(&changes, len / 2 + 10), Some(Some(0)));
+        assert_eq!(changes.region_at(0), Some((0, len)));
+    }
+
+    #[test]
+    fn replacing_keeps_the_saved_state_of_changed_bytes() {
+        let mut changes = Changes::new();
+        changes.apply(&replace(2, &[1, 2], &[5, 6]));
+        changes.apply(&Edit::Insert {
+            offset: 4,
+            bytes: vec![7],
+        });
+
+        // Only byte 3 gets its saved value back.
+        changes.apply(&replace(1, &[0, 5, 6, 7, 3], &[9, 9, 2, 9, 9]));
+        assert_eq!(original(&changes, 0), None);
+        assert_eq!(original(&changes, 1), Some(Some(0)));
+        assert_eq!(original(&changes, 2), Some(Some(1)));
+        assert_eq!(original(&changes, 3), None);
+        assert_eq!(original(&changes, 4), Some(None));
+        assert_eq!(original(&changes, 5), Some(Some(3)));
+        assert_eq!(changes.region_at(4), Some((4, 6)));
+        assert_eq!(changes.next_region(2), Some(4));
+        assert_eq!(changes.previous_region(4), Some(1));
+        assert_eq!(
+            changes.between(2, 5),
+            vec![(0, Original::Replaced(vec![1])), (2, Original::Inserted(1))]
+        );
+    }
+
+    /// Original value of the byte at `offset`: `None` if the byte is
+    /// unchanged, and `Some(None)` if it was inserted.
+    fn original(changes: &Changes, offset: usize) -> Option<Option<u8>> {
+        changes
+            .run_at(offset)
+            .map(|(start, run)| run.get(offset - start))
+    }
+
+    fn remove_changed(edit: &Edit) -> &[(usize, Original)] {
+        match edit {
+            Edit::Remove { changed, .. } => changed,
+            _ => &[],
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Byte order of the words shown by `HexArea`.
+
+/// Order of the bytes of a word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    /// Least significant byte first, like x86 and most ARM systems.
+    Little,
+    /// Most significant byte first, like network protocols.
+    Big,
+}
+
+impl Endian {
+    /// Reads the word made of `bytes`, at most 8 of them.
+    pub fn read(self, bytes: &[u8]) -> u64 {
+        let fold = |value, &byte| value << 8 | u64::from(byte);
+        match self {
+            Endian::Little => bytes.iter().rev().fold(0, fold),
+            Endian::Big => bytes.iter().fold(0, fold),
+        }
+    }
+
+    /// Writes `value` as a word of `size` bytes.
+    pub fn write(self, value: u64, size: usize) -> Vec<u8> {
+        let bytes = value.to_le_bytes()[..size].to_vec();
+        match self {
+            Endian::Little => bytes,
+            Endian::Big => bytes.into_iter().rev().collect(),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Edit journal backing undo and redo in `HexArea`.
+
+use super::changes::Original;
+
+/// A single modification of the content.
+#[derive(Clone, Debug)]
+pub enum Edit {
+    /// `bytes` were inserted at `offset`.
+    Insert { offset: usize, bytes: Vec<u8> },
+    /// `bytes` were removed from `offset`.
+    ///
+    /// `changed` lists the runs of removed bytes which had been changed
+    /// since the last save, by position from `offset`, with their saved
+    /// state, so that undoing the removal can restore them as they were.
+    Remove {
+        offset: usize,
+        bytes: Vec<u8>,
+        changed: Vec<(usize, Original)>,
+    },
+    /// The bytes from `offset` were overwritten, `old` and `new` having the
+    /// same length.
+    Replace {
+        offset: usize,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+impl Edit {
+    /// Returns the edit cancelling this one.
+    pub fn inverse(&self) -> Edit {
+        match self {
+            Edit::Insert { offset, bytes } => Edit::Remove {
+                offset: *offset,
+                bytes: bytes.clone(),
+                changed: vec![(0, Original::Inserted(bytes.len()))],
+            },
+            Edit::Remove { offset, bytes, .. } => Edit::Insert {
+                offset: *offset,
+                bytes: bytes.clone(),
+            },
+            Edit::Replace { offset, old, new } => Edit::Replace {
+                offset: *offset,
+                old: new.clone(),
+                new: old.clone(),
+            },
+        }
+    }
+
+    /// Offset where this edit takes place.
+    pub fn offset(&self) -> usize {
+        match self {
+            Edit::Insert { offset, .. }
+            | Edit::Remove { offset, .. }
+            | Edit::Replace { offset, .. } => *offset,
+        }
+    }
+}
+
+/// Group of edits undone and redone as a whole.
+///
+/// Like Vim, everything typed during a single insert-mode session ends up
+/// in the same change.
+#[derive(Clone, Debug, Default)]
+pub struct Change {
+    /// Unique identifier, used to know whether the content was saved.
+    id: usize,
+    /// Edits, in the order they were applied.
+    pub edits: Vec<Edit>,
+}
+
+impl Change {
+    /// Offset where the cursor should be put after undoing or redoing this
+    /// change.
+    pub fn cursor(&self) -> usize {
+        self.edits.first().map(Edit::offset).unwrap_or(0)
+    }
+}
+
+/// Undo and redo stacks.
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Change>,
+    redo_stack: Vec<Change>,
+
+    /// Change currently being built, if any.
+    pending: Option<Change>,
+
+    /// Identifier given to the next change.
+    next_id: usize,
+
+    /// Identifier of the last change applied when the content was saved,
+    /// or 0 when it had no changes.
+    saved_id: usize,
+
+    /// Changes with an identifier up to this one were made before the
+    /// content was last saved.
+    last_id_before_save: usize,
+}
+
+impl History {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// Starts grouping the following edits into a single change.
+    pub fn begin(&mut self) {
+        self.commit();
+        self.pending = Some(self.new_change());
+    }
+
+    fn new_change(&mut self) -> Change {
+        // Identifiers start at 1, 0 standing for the unchanged content.
+        self.next_id += 1;
+        Change {
+            id: self.next_id,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Identifier of the last change applied.
+    fn current_id(&self) -> usize {
+        self.undo_stack.last().map_or(0, |change| change.id)
+    }
+
+    /// Closes the change started by `begin`.
+    pub fn commit(&mut self) {
+        if let Some(change) = self.pending.take() {
+            if !change.edits.is_empty() {
+                self.undo_stack.push(change);
+            }
+        }
+    }
+
+    /// Records an edit that was just applied.
+    ///
+    /// Outside of a group, the edit is a change of its own.
+    pub fn record(&mut self, edit: Edit) {
+        self.redo_stack.clear();
+
+        match self.pending {
+            Some(ref mut change) => change.edits.push(edit),
+            None => {
+                let mut change = self.new_change();
+                change.edits.push(edit);
+                self.undo_stack.push(change);
+            }
+        }
+    }
+
+    /// Returns the last change, to be reverted by the caller.
+    pub fn undo(&mut self) -> Option<Change> {
+        self.commit();
+
+        let change = self.undo_stack.pop()?;
+        self.redo_stack.push(change.clone());
+        Some(change)
+    }
+
+    /// Returns the last undone change, to be re-applied by the caller.
+    pub fn redo(&mut self) -> Option<Change> {
+        self.commit();
+
+        let change = self.redo_stack.pop()?;
+        self.undo_stack.push(change.clone());
+        Some(change)
+    }
+
+    /// Returns `true` if changes were made since the content was last
+    /// saved.
+    ///
+    /// Undoing back to the saved state makes the content unmodified again.
+    pub fn is_modified(&self) -> bool {
+        let pending = self
+            .pending
+            .as_ref()
+            .map_or(false, |change| !change.edits.is_empty());
+
+        pending || self.current_id() != self.saved_id
+    }
+
+    /// Remembers the current state as the saved one.
+    pub fn mark_saved(&mut self) {
+        // A change still being built is closed, so that it can be told apart
+        // from the edits following the save.
+        let pending = self.pending.is_some();
+        self.commit();
+        self.saved_id = self.current_id();
+        self.last_id_before_save = self.next_id;
+        if pending {
+            self.pending = Some(self.new_change());
+        }
+    }
+
+    /// Returns `true` if `change` was made before the content was last
+    /// saved.
+    pub fn predates_save(&self, change: &Change) -> bool {
+        change.id <= self.last_id_before_save
+    }
+
+    /// Forgets every recorded change.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending = None;
+        self.saved_id = 0;
+        self.last_id_before_save = self.next_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(offset: usize) -> Edit {
+        Edit::Replace {
+            offset,
+            old: vec![0],
+            new: vec![1],
+        }
+    }
+
+    #[test]
+    fn groups_edits_between_begin_and_commit() {
+        let mut history = History::new();
+        history.record(replace(0));
+        history.begin();
+        history.record(replace(1));
+        history.record(replace(2));
+        history.commit();
+
+        assert_eq!(history.undo().map(|change| change.edits.len()), Some(2));
+        assert_eq!(history.undo().map(|change| change.cursor()), Some(0));
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn empty_groups_are_dropped() {
+        let mut history = History::new();
+        history.record(replace(0));
+        history.begin();
+        history.commit();
+
+        assert!(history.undo().is_some());
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn tracks_the_saved_state_across_undo_and_redo() {
+        let mut history = History::new();
+        assert!(!history.is_modified());
+
+        history.record(replace(0));
+        history.record(replace(1));
+        history.mark_saved();
+        assert!(!history.is_modified());
+
+        history.undo();
+        assert!(history.is_modified());
+        history.redo();
+        assert!(!history.is_modified());
+
+        // Undoing past the save and making a new change loses the saved
+        // state for good.
+        history.undo();
+        history.record(replace(2));
+        assert!(history.is_modified());
+        assert!(history.redo().is_none());
+        history.undo();
+        assert!(history.is_modified());
+    }
+
+    #[test]
+    fn saving_splits_the_pending_change() {
+        let mut history = History::new();
+        history.begin();
+        history.record(replace(0));
+        history.mark_saved();
+        assert!(!history.is_modified());
+
+        history.record(replace(1));
+        assert!(history.is_modified());
+        history.commit();
+
+        // Only the edit made after the save is undone at first.
+        let change = history.undo().unwrap();
+        assert_eq!(change.cursor(), 1);
+        assert!(!history.predates_save(&change));
+        assert!(!history.is_modified());
+
+        let change = history.undo().unwrap();
+        assert_eq!(change.cursor(), 0);
+        assert!(history.predates_save(&change));
+        assert!(history.is_modified());
+    }
+}
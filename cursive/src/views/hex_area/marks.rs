@@ -0,0 +1,213 @@
+//! Marks and jump list of `HexArea`.
+//!
+//! Both remember byte offsets, which follow the bytes they point to when
+//! content is inserted or removed before them.
+
+use super::history::Edit;
+use std::collections::BTreeMap;
+
+/// Maximum number of positions kept in the jump list, like in Vim.
+const MAX_JUMPS: usize = 100;
+
+/// Offset of the byte at `offset` once `edit` is applied.
+///
+/// Returns `None` if that byte was removed.
+fn shifted(offset: usize, edit: &Edit) -> Option<usize> {
+    match edit {
+        Edit::Insert { offset: at, bytes } if offset >= *at => {
+            Some(offset + bytes.len())
+        }
+        Edit::Remove {
+            offset: at, bytes, ..
+        } if offset >= *at => {
+            offset.checked_sub(bytes.len()).filter(|&new| new >= *at)
+        }
+        _ => Some(offset),
+    }
+}
+
+/// Marks `a` to `z`, set with `m`.
+#[derive(Debug, Default)]
+pub struct Marks(BTreeMap<char, usize>);
+
+impl Marks {
+    /// Creates an empty set of marks.
+    pub fn new() -> Self {
+        Marks::default()
+    }
+
+    /// Returns `true` if `name` can be given after `m`.
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_lowercase()
+    }
+
+    pub fn set(&mut self, name: char, offset: usize) {
+        self.0.insert(name, offset);
+    }
+
+    pub fn get(&self, name: char) -> Option<usize> {
+        self.0.get(&name).cloned()
+    }
+
+    /// Lists the marks which are set, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.0.iter().map(|(&name, &offset)| (name, offset))
+    }
+
+    /// Moves the marks along with their bytes after `edit` was applied.
+    ///
+    /// Like Vim, marks on removed bytes are deleted.
+    pub fn apply(&mut self, edit: &Edit) {
+        let marks = std::mem::replace(&mut self.0, BTreeMap::new());
+        self.0 = marks
+            .into_iter()
+            .filter_map(|(name, offset)| {
+                shifted(offset, edit).map(|offset| (name, offset))
+            })
+            .collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Positions the cursor jumped from, browsed with Ctrl-O and Ctrl-I.
+#[derive(Debug, Default)]
+pub struct JumpList {
+    jumps: Vec<usize>,
+
+    /// Position in `jumps` while going back and forth, or `jumps.len()`
+    /// when not browsing the list.
+    index: usize,
+}
+
+impl JumpList {
+    /// Creates an empty jump list.
+    pub fn new() -> Self {
+        JumpList::default()
+    }
+
+    /// Records the position the cursor is about to jump from.
+    pub fn push(&mut self, offset: usize) {
+        // Like Vim, a position is only kept once, at its latest place.
+        self.jumps.retain(|&jump| jump != offset);
+        self.jumps.push(offset);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.index = self.jumps.len();
+    }
+
+    /// Returns the position to go back to from `current`, if any.
+    pub fn back(&mut self, current: usize) -> Option<usize> {
+        if self.index == 0 {
+            return None;
+        }
+
+        // Remember where we come from, so that Ctrl-I can return there.
+        if self.index == self.jumps.len() {
+            self.push(current);
+            self.index -= 1;
+        }
+        self.index -= 1;
+        self.jumps.get(self.index).cloned()
+    }
+
+    /// Returns the position to go forward to, if any.
+    pub fn forward(&mut self) -> Option<usize> {
+        if self.index + 1 >= self.jumps.len() {
+            return None;
+        }
+
+        self.index += 1;
+        self.jumps.get(self.index).cloned()
+    }
+
+    /// Moves the positions along with their bytes after `edit` was
+    /// applied.
+    ///
+    /// Positions on removed bytes move to where the removal took place.
+    pub fn apply(&mut self, edit: &Edit) {
+        for jump in &mut self.jumps {
+            *jump = shifted(*jump, edit).unwrap_or_else(|| edit.offset());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.jumps.clear();
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(offset: usize, len: usize) -> Edit {
+        Edit::Insert {
+            offset,
+            bytes: vec![0; len],
+        }
+    }
+
+    fn remove(offset: usize, len: usize) -> Edit {
+        Edit::Remove {
+            offset,
+            bytes: vec![0; len],
+            changed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn marks_follow_their_bytes() {
+        let mut marks = Marks::new();
+        marks.set('a', 4);
+        marks.set('b', 10);
+
+        marks.apply(&insert(4, 3));
+        assert_eq!(marks.get('a'), Some(7));
+        assert_eq!(marks.get('b'), Some(13));
+
+        marks.apply(&remove(8, 2));
+        assert_eq!(marks.get('a'), Some(7));
+        assert_eq!(marks.get('b'), Some(11));
+
+        marks.apply(&Edit::Replace {
+            offset: 11,
+            old: vec![0],
+            new: vec![1],
+        });
+        assert_eq!(marks.get('b'), Some(11));
+    }
+
+    #[test]
+    fn marks_on_removed_bytes_are_deleted() {
+        let mut marks = Marks::new();
+        marks.set('a', 4);
+        marks.set('b', 5);
+        marks.set('c', 6);
+
+        marks.apply(&remove(4, 2));
+        assert_eq!(marks.get('a'), None);
+        assert_eq!(marks.get('b'), None);
+        assert_eq!(marks.get('c'), Some(4));
+        assert_eq!(marks.iter().count(), 1);
+    }
+
+    #[test]
+    fn jumps_on_removed_bytes_move_to_the_removal() {
+        let mut jumps = JumpList::new();
+        jumps.push(2);
+        jumps.push(20);
+        jumps.push(30);
+
+        jumps.apply(&remove(15, 10));
+        jumps.apply(&insert(0, 1));
+        assert_eq!(jumps.back(0), Some(21));
+        assert_eq!(jumps.back(0), Some(16));
+        assert_eq!(jumps.back(0), Some(3));
+        assert_eq!(jumps.back(0), None);
+        assert_eq!(jumps.forward(), Some(16));
+    }
+}
@@ -0,0 +1,3380 @@
+// Modified version of TextArea, more suited to hex editing.
+// Modifications by Alex Garrett <alex@alexgarrett.tech>.
+
+mod buffer;
+mod changes;
+mod endian;
+mod history;
+mod marks;
+mod radix;
+mod registers;
+mod search;
+
+pub use self::buffer::Buffer;
+use self::changes::{Changes, Original};
+pub use self::endian::Endian;
+use self::history::{Edit, History};
+use self::marks::{JumpList, Marks};
+pub use self::radix::Radix;
+use self::registers::{Registers, UNNAMED};
+use self::search::Pattern;
+pub use self::search::{SearchMode, TextEncoding};
+use crate::direction::Direction;
+use crate::div::div_up;
+use crate::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use crate::rect::Rect;
+use crate::theme::{BaseColor, Color, ColorStyle, Effect, PaletteColor};
+use crate::types::EditorMode;
+use crate::vec::Vec2;
+use crate::view::{ScrollBase, SizeCache, View};
+use crate::{Cursive, Printer, With, XY};
+use log::debug;
+use std::borrow::Cow;
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
+
+// Spaces between the address column and the hex pane
+const ADDRESS_PADDING: usize = 2;
+
+// Bytes shown on each row unless set otherwise, like most hex editors
+const DEFAULT_COLUMNS: usize = 16;
+
+// Keys replayed at most by a single macro, which stops a macro calling
+// itself forever
+const MAX_PLAYED_EVENTS: usize = 1_000_000;
+
+// Bytes written at most by a single put, which are all held in memory
+const MAX_PUT_LEN: usize = 1 << 30;
+
+// Keys of normal and visual modes which start a change, refused in
+// read-only mode
+const CHANGE_KEYS: &str = "iaRxXdrpP.u";
+
+/// Column of the view the cursor is in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Pane {
+    /// Bytes shown as digits, in hex unless set otherwise.
+    Hex,
+    /// Bytes shown as characters.
+    Text,
+}
+
+/// Last pattern searched for, repeated by `n` and `N`.
+struct Search {
+    /// Query as typed by the user.
+    query: String,
+    pattern: Pattern,
+    backward: bool,
+}
+
+/// Change which can be repeated with `.`.
+#[derive(Clone, Debug)]
+enum Action {
+    /// `r`: overwrite the bytes under the cursor with `bytes`, `count`
+    /// times.
+    Replace { bytes: Vec<u8>, count: usize },
+    /// `d` followed by a motion, as well as `x` and `X`.
+    Delete {
+        motion: String,
+        count: Option<usize>,
+    },
+    /// `p`, or `P` when `insert` is `true`.
+    Put {
+        register: Option<char>,
+        insert: bool,
+        count: Option<usize>,
+    },
+    /// `d` in visual mode: remove `len` bytes from the cursor.
+    DeleteBytes { len: usize },
+    /// `r` in visual mode: overwrite `len` bytes from the cursor with
+    /// `pattern`.
+    Fill { pattern: Vec<u8>, len: usize },
+}
+
+impl Action {
+    /// Returns the same action, repeated according to a new count.
+    ///
+    /// Changes made in visual mode cover `count` times as many bytes.
+    fn with_count(self, count: usize) -> Self {
+        match self {
+            Action::Replace { bytes, .. } => Action::Replace { bytes, count },
+            Action::Delete { motion, .. } => Action::Delete {
+                motion,
+                count: Some(count),
+            },
+            Action::Put {
+                register, insert, ..
+            } => Action::Put {
+                register,
+                insert,
+                count: Some(count),
+            },
+            Action::DeleteBytes { len } => Action::DeleteBytes {
+                len: len.saturating_mul(count),
+            },
+            Action::Fill { pattern, len } => Action::Fill {
+                pattern,
+                len: len.saturating_mul(count),
+            },
+        }
+    }
+}
+
+/// Message displayed next to the editor mode, until the next event.
+enum Status {
+    Info(String),
+    Error(String),
+}
+
+/// Multi-line hex editor which can be navigated similarly to Vim.
+pub struct HexArea {
+    /// Raw bytes being edited.
+    content: Buffer,
+
+    /// When `false`, we don't take any input.
+    enabled: bool,
+
+    /// Base for scrolling features
+    scrollbase: ScrollBase,
+
+    /// Cache to avoid re-computing layout on no-op events
+    size_cache: Option<XY<SizeCache>>,
+    last_size: Vec2,
+
+    /// Offset of the currently selected byte.
+    cursor: usize,
+
+    /// Selected digit within the current cell, counting from the left: 0
+    /// for the high nibble of a byte in hex, 7 for its lowest bit in binary.
+    ///
+    /// Always 0 in the text pane.
+    digit: usize,
+
+    /// Pane the cursor is in.
+    pane: Pane,
+
+    /// Current editor mode.
+    ///
+    /// User inputs have different effects in different modes, much like Vim.
+    mode: EditorMode,
+
+    /// Number of bytes displayed on each row.
+    ///
+    /// Invariant: never zero.
+    bytes_per_line: usize,
+
+    /// Number of bytes per row asked for, or `None` to fit as many as the
+    /// width allows.
+    columns: Option<usize>,
+
+    /// Number of bytes grouped together in the hex pane, with an extra
+    /// space between groups. 0 and 1 mean no grouping.
+    group: usize,
+
+    /// Radix bytes are written in, in the hex pane.
+    radix: Radix,
+
+    /// Number of bytes shown together as a word in each cell of the hex
+    /// pane: 1, 2, 4 or 8.
+    word_size: usize,
+
+    /// Order of the bytes of each word.
+    endian: Endian,
+
+    /// Address of the first byte, added to the offsets shown in the address
+    /// column.
+    base_address: usize,
+
+    /// When `true`, addresses are shown in decimal rather than hex.
+    decimal_addresses: bool,
+
+    /// When `true`, the content may not be changed.
+    readonly: bool,
+
+    /// Journal of edits, for undo and redo.
+    history: History,
+
+    /// Offset where the selection started, in visual modes.
+    visual_anchor: usize,
+
+    /// Range of the last visual selection, used by Ex commands such as
+    /// `:'<,'>w`.
+    last_selection: Option<(usize, usize)>,
+
+    /// Bytes yanked and deleted.
+    registers: Registers,
+
+    /// Register given with `"x` for the next yank, delete or put.
+    register_name: Option<char>,
+
+    /// Last change made in normal or visual mode, repeated by `.`.
+    last_change: Option<Action>,
+
+    /// Events recorded with `q`, by register name.
+    macros: BTreeMap<char, Vec<Event>>,
+
+    /// Register being recorded into, with the events received so far.
+    recording: Option<(char, Vec<Event>)>,
+
+    /// Register last played with `@`, played again by `@@`.
+    last_macro: Option<char>,
+
+    /// Events waiting to be replayed by a macro.
+    playback: VecDeque<Event>,
+
+    /// `true` while the events of a macro are being replayed.
+    playing: bool,
+
+    /// Set when a command could not be carried out, such as a motion past
+    /// the end of the content. This stops macros.
+    failed: bool,
+
+    /// Bytes modified since the content was last saved.
+    changes: Changes,
+
+    /// Marks set with `m`.
+    marks: Marks,
+
+    /// Positions jumped from, browsed with Ctrl-O and Ctrl-I or Ctrl-N.
+    jumps: JumpList,
+
+    /// Keys typed so far of a multi-key command such as `]c` or `gg`.
+    pending: String,
+
+    /// Count typed before a command, such as the 16 of `16l`.
+    count: Option<usize>,
+
+    last_search: Option<Search>,
+
+    status: Option<Status>,
+
+    /// Ex command being typed in command mode, without the leading `:`.
+    command: String,
+
+    /// Callback when an Ex command is submitted.
+    on_command: Option<Rc<dyn Fn(&mut Cursive, &str)>>,
+
+    /// Callback when the cursor moves or the content changes.
+    on_cursor_move: Option<Rc<dyn Fn(&mut Cursive, usize)>>,
+
+    /// Number of edits applied so far, to tell when the content changed.
+    revision: usize,
+}
+
+impl HexArea {
+    /// Creates a new HexArea from a buffer of bytes, either a `Vec<u8>` or
+    /// a `Buffer`.
+    pub fn from<B: Into<Buffer>>(bytes: B) -> Self {
+        let mut hex_area = HexArea {
+            content: Buffer::default(),
+            enabled: true,
+            scrollbase: ScrollBase::new().right_padding(0),
+            size_cache: None,
+            last_size: Vec2::zero(),
+            cursor: 0,
+            digit: 0,
+            pane: Pane::Hex,
+            mode: EditorMode::Normal,
+            bytes_per_line: 1,
+            columns: Some(DEFAULT_COLUMNS),
+            group: 1,
+            radix: Radix::Hex,
+            word_size: 1,
+            endian: Endian::Little,
+            base_address: 0,
+            decimal_addresses: false,
+            readonly: false,
+            history: History::new(),
+            visual_anchor: 0,
+            last_selection: None,
+            registers: Registers::new(),
+            register_name: None,
+            last_change: None,
+            macros: BTreeMap::new(),
+            recording: None,
+            last_macro: None,
+            playback: VecDeque::new(),
+            playing: false,
+            failed: false,
+            changes: Changes::new(),
+            marks: Marks::new(),
+            jumps: JumpList::new(),
+            pending: String::new(),
+            count: None,
+            last_search: None,
+            status: None,
+            command: String::new(),
+            on_command: None,
+            on_cursor_move: None,
+            revision: 0,
+        };
+
+        hex_area.set_buffer(bytes.into());
+
+        hex_area
+    }
+
+    /// Retrieves the bytes currently held by the view.
+    pub fn get_buffer(&self) -> &Buffer {
+        &self.content
+    }
+
+    /// Retrieves the bytes currently held by the view, to save them.
+    pub fn get_buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.content
+    }
+
+    fn invalidate(&mut self) {
+        self.size_cache = None;
+    }
+
+    /// Returns the offset of the byte under the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Moves the cursor to the first digit of the given byte.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `cursor` is past the end of the content and
+    /// the content is not empty.
+    pub fn set_cursor(&mut self, cursor: usize) {
+        assert!(cursor < self.content.len() || cursor == 0);
+        self.cursor = cursor;
+        self.digit = 0;
+
+        let focus = self.selected_row();
+        self.scrollbase.scroll_to(focus);
+    }
+
+    /// Sets the bytes held by the view.
+    pub fn set_bytes(&mut self, bytes: Vec<u8>) {
+        self.set_buffer(Buffer::from(bytes));
+    }
+
+    /// Sets the bytes held by the view, such as a file opened with
+    /// `Buffer::open`.
+    pub fn set_buffer(&mut self, buffer: Buffer) {
+        self.content = buffer;
+        self.history.clear();
+        self.changes.clear();
+        self.marks.clear();
+        self.jumps.clear();
+        self.last_selection = None;
+
+        // Make sure we are within the bounds.
+        self.cursor = min(self.cursor, self.last_offset());
+
+        if let Some(size) = self.size_cache.map(|s| s.map(|s| s.value)) {
+            self.invalidate();
+            self.compute_rows(size);
+        }
+    }
+
+    /// Sets the bytes held by the view.
+    ///
+    /// Chainable variant.
+    pub fn bytes(self, bytes: Vec<u8>) -> Self {
+        self.with(|s| s.set_bytes(bytes))
+    }
+
+    /// Sets the bytes held by the view.
+    ///
+    /// Chainable variant.
+    pub fn buffer(self, buffer: Buffer) -> Self {
+        self.with(|s| s.set_buffer(buffer))
+    }
+
+    /// Sets a callback to be called when an Ex command is submitted.
+    ///
+    /// Commands are typed after pressing `:` in normal mode. `callback` will
+    /// be given the command line, without the leading `:`.
+    pub fn set_on_command<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut Cursive, &str) + 'static,
+    {
+        self.on_command = Some(Rc::new(callback));
+    }
+
+    /// Sets a callback to be called when an Ex command is submitted.
+    ///
+    /// Chainable variant.
+    pub fn on_command<F>(self, callback: F) -> Self
+    where
+        F: Fn(&mut Cursive, &str) + 'static,
+    {
+        self.with(|v| v.set_on_command(callback))
+    }
+
+    /// Sets a callback to be called when the cursor moves.
+    ///
+    /// It is also called when the bytes under the cursor may have changed,
+    /// after an edit. `callback` will be given the offset of the cursor.
+    pub fn set_on_cursor_move<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut Cursive, usize) + 'static,
+    {
+        self.on_cursor_move = Some(Rc::new(callback));
+    }
+
+    /// Sets a callback to be called when the cursor moves.
+    ///
+    /// Chainable variant.
+    pub fn on_cursor_move<F>(self, callback: F) -> Self
+    where
+        F: Fn(&mut Cursive, usize) + 'static,
+    {
+        self.with(|v| v.set_on_cursor_move(callback))
+    }
+
+    /// Sets the number of bytes shown on each row.
+    ///
+    /// With `None`, as many bytes as the width allows are shown. Fewer bytes
+    /// are also shown when the view is too narrow for `columns`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `columns` is `Some(0)`.
+    pub fn set_columns(&mut self, columns: Option<usize>) {
+        assert_ne!(columns, Some(0));
+        self.columns = columns;
+        self.relayout();
+    }
+
+    /// Groups cells by `group` in the hex pane, with an extra space between
+    /// groups. 0 or 1 disables grouping.
+    ///
+    /// Cells are bytes, or words when `set_word_size` was called.
+    pub fn set_group(&mut self, group: usize) {
+        self.group = group;
+        self.relayout();
+    }
+
+    /// Writes bytes in `radix` in the hex pane.
+    ///
+    /// Editing then also takes digits in that radix.
+    pub fn set_radix(&mut self, radix: Radix) {
+        self.radix = radix;
+        self.digit = 0;
+        self.relayout();
+    }
+
+    /// Shows bytes as words of `size` bytes in the hex pane, like `xxd -e`.
+    ///
+    /// Rows then hold whole words, and editing sets a word at a time.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `size` is not 1, 2, 4 or 8.
+    pub fn set_word_size(&mut self, size: usize) {
+        assert!([1, 2, 4, 8].contains(&size));
+        self.word_size = size;
+        self.digit = 0;
+        self.relayout();
+    }
+
+    /// Sets the byte order of the words shown in the hex pane.
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    /// Shows addresses starting at `base` rather than 0, such as the address
+    /// a flash image is mapped at.
+    pub fn set_base_address(&mut self, base: usize) {
+        self.base_address = base;
+        self.relayout();
+    }
+
+    /// Returns the address of the first byte.
+    pub fn base_address(&self) -> usize {
+        self.base_address
+    }
+
+    /// Shows addresses in decimal rather than hex.
+    pub fn set_decimal_addresses(&mut self, decimal: bool) {
+        self.decimal_addresses = decimal;
+        self.relayout();
+    }
+
+    /// Formats the address of the byte at `offset`, like in the address
+    /// column.
+    pub fn format_address(&self, offset: usize) -> String {
+        let address = self.base_address.saturating_add(offset);
+        let width = self.address_width();
+        if self.decimal_addresses {
+            format!("{:01$}", address, width)
+        } else {
+            format!("{:01$X}", address, width)
+        }
+    }
+
+    /// Number of digits of the addresses.
+    ///
+    /// Hex addresses take 8, 12 or 16 digits depending on the last address,
+    /// and decimal ones as many digits as the largest value of those widths.
+    fn address_width(&self) -> usize {
+        let last = self.base_address.saturating_add(self.content.len()) as u64;
+        let digits = if last <= 0xFFFF_FFFF {
+            8
+        } else if last <= 0xFFFF_FFFF_FFFF {
+            12
+        } else {
+            16
+        };
+
+        if self.decimal_addresses {
+            (u64::MAX >> (64 - 4 * digits)).to_string().len()
+        } else {
+            digits
+        }
+    }
+
+    /// Recomputes the rows after a setting changed, keeping the cursor in
+    /// view.
+    fn relayout(&mut self) {
+        self.invalidate();
+        self.compute_rows(self.last_size);
+        let focus = self.selected_row();
+        self.scrollbase.scroll_to(focus);
+    }
+
+    /// Returns `true` if the content was modified since it was last saved.
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified()
+    }
+
+    /// Marks the current content as saved.
+    ///
+    /// Call this after writing the content to disk.
+    pub fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.changes.clear();
+    }
+
+    /// Displays a message in the status line, until the next event.
+    pub fn set_status<S: Into<String>>(&mut self, message: S) {
+        self.status = Some(Status::Info(message.into()));
+    }
+
+    /// Displays an error in the status line, until the next event.
+    pub fn set_error<S: Into<String>>(&mut self, message: S) {
+        self.status = Some(Status::Error(message.into()));
+        self.failed = true;
+    }
+
+    /// Disables this view.
+    ///
+    /// A disabled view cannot be selected.
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Disables this view.
+    ///
+    /// Chainable variant.
+    pub fn disabled(self) -> Self {
+        self.with(Self::disable)
+    }
+
+    /// Re-enables this view.
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Re-enables this view.
+    ///
+    /// Chainable variant.
+    pub fn enabled(self) -> Self {
+        self.with(Self::enable)
+    }
+
+    /// Returns `true` if this view is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Prevents or allows changes to the content.
+    ///
+    /// In read-only mode, keys which would change the content, such as `i`
+    /// or `x`, only display an error.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+    }
+
+    /// Prevents or allows changes to the content.
+    ///
+    /// Chainable variant.
+    pub fn readonly(self, readonly: bool) -> Self {
+        self.with(|s| s.set_readonly(readonly))
+    }
+
+    /// Returns `true` if the content may not be changed.
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Offset of the last byte, or 0 if the content is empty.
+    fn last_offset(&self) -> usize {
+        self.content.len().saturating_sub(1)
+    }
+
+    /// Furthest offset the cursor may currently reach.
+    ///
+    /// In insert and replace modes, the cursor may sit right after the last
+    /// byte so that new bytes can be appended.
+    fn max_cursor(&self) -> usize {
+        if self.mode.is_editing() {
+            self.content.len()
+        } else {
+            self.last_offset()
+        }
+    }
+
+    /// Number of rows needed to display the whole content.
+    ///
+    /// This includes room for a cursor placed right after the last byte, so
+    /// there is always at least 1 row, even when the content is empty.
+    fn row_count(&self) -> usize {
+        div_up(self.content.len() + 1, self.bytes_per_line)
+    }
+
+    /// Finds the row containing the byte at the given offset
+    fn row_at(&self, offset: usize) -> usize {
+        offset / self.bytes_per_line
+    }
+
+    fn col_at(&self, offset: usize) -> usize {
+        offset % self.bytes_per_line
+    }
+
+    /// Finds the row containing the cursor
+    fn selected_row(&self) -> usize {
+        self.row_at(self.cursor)
+    }
+
+    fn selected_col(&self) -> usize {
+        self.col_at(self.cursor)
+    }
+
+    /// Number of rows visible at once.
+    fn page_height(&self) -> usize {
+        max(1, self.scrollbase.view_height)
+    }
+
+    /// Scrolls the view by the given number of rows, taking the cursor
+    /// along.
+    fn scroll_up(&mut self, rows: usize) {
+        let rows = min(rows, self.row_count());
+        self.scrollbase.scroll_up(rows);
+        repeat(rows, || self.move_up());
+    }
+
+    /// Scrolls the view by the given number of rows, taking the cursor
+    /// along.
+    fn scroll_down(&mut self, rows: usize) {
+        // Scrolling past the end makes no difference, and the scroll base
+        // would overflow.
+        let rows = min(rows, self.row_count());
+        self.scrollbase.scroll_down(rows);
+        repeat(rows, || self.move_down());
+    }
+
+    /// Moves the cursor to the given row, staying in the same column.
+    fn move_to_row(&mut self, row: usize) {
+        let row = min(row, self.row_count() - 1);
+        let col = self.selected_col();
+        self.cursor = min(row * self.bytes_per_line + col, self.max_cursor());
+    }
+
+    /// Returns `false` if the cursor is already on the first row.
+    fn move_up(&mut self) -> bool {
+        if self.selected_row() == 0 {
+            return false;
+        }
+
+        self.cursor -= self.bytes_per_line;
+        true
+    }
+
+    /// Returns `false` if the cursor is already on the last row.
+    fn move_down(&mut self) -> bool {
+        if self.selected_row() + 1 >= self.row_count() {
+            return false;
+        }
+
+        // The last row may be shorter than the others, in which case we
+        // land on its last byte.
+        self.cursor =
+            min(self.cursor + self.bytes_per_line, self.max_cursor());
+        true
+    }
+
+    /// Returns `true` if horizontal moves skip whole bytes rather than
+    /// digits.
+    ///
+    /// This is the case in all modes but normal mode, and in the text pane.
+    fn moves_by_byte(&self) -> bool {
+        self.mode.is_editing()
+            || self.mode.is_visual()
+            || self.pane == Pane::Text
+    }
+
+    /// Digit selected when the cursor lands at the end of a byte.
+    fn last_digit(&self) -> usize {
+        if self.moves_by_byte() {
+            0
+        } else {
+            self.cell_digits() - 1
+        }
+    }
+
+    /// Number of bytes the cursor moves by horizontally: a whole word in
+    /// the hex pane.
+    fn step(&self) -> usize {
+        match self.pane {
+            Pane::Hex => self.word_size,
+            Pane::Text => 1,
+        }
+    }
+
+    /// Offset of the first byte of the cell holding `offset`.
+    fn cell_start(&self, offset: usize) -> usize {
+        offset - offset % self.word_size
+    }
+
+    /// Offset of the byte, or of the word in the hex pane, the cursor is
+    /// on.
+    fn cursor_start(&self) -> usize {
+        self.cursor - self.cursor % self.step()
+    }
+
+    /// Moves the cursor one digit to the left.
+    ///
+    /// Moves by whole bytes or words instead when `moves_by_byte` is
+    /// `true`. Wraps the previous line if required. Returns `false` if the
+    /// cursor is already at the start.
+    fn move_left(&mut self) -> bool {
+        let start = self.cursor_start();
+        if self.digit > 0 && !self.moves_by_byte() {
+            self.digit -= 1;
+        } else if start > 0 {
+            self.cursor = start - self.step();
+            self.digit = self.last_digit();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Moves the cursor one digit to the right.
+    ///
+    /// Moves by whole bytes or words instead when `moves_by_byte` is
+    /// `true`. Jumps to the next line if required. Returns `false` if the
+    /// cursor is already at the end.
+    fn move_right(&mut self) -> bool {
+        let next = self.cursor_start() + self.step();
+        if self.digit < self.last_digit() {
+            self.digit += 1;
+        } else if next <= self.max_cursor() {
+            self.cursor = next;
+            self.digit = 0;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Moves the cursor to the start of the next byte, or word in the hex
+    /// pane.
+    fn next_byte(&mut self) -> bool {
+        let next = self.cursor_start() + self.step();
+        if next > self.last_offset() {
+            return false;
+        }
+
+        self.cursor = next;
+        self.digit = 0;
+        true
+    }
+
+    /// Moves the cursor to the start of the current byte or word, or of the
+    /// previous one if it is already there.
+    fn previous_byte(&mut self) -> bool {
+        if self.is_at_start() {
+            return false;
+        }
+
+        let start = self.cursor_start();
+        self.cursor = if self.digit == 0 {
+            start - self.step()
+        } else {
+            start
+        };
+        self.digit = 0;
+        true
+    }
+
+    fn is_at_start(&self) -> bool {
+        self.cursor_start() == 0 && self.digit == 0
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.cursor_start() + self.step() > self.max_cursor()
+            && self.digit == self.last_digit()
+    }
+
+    /// Moves the cursor to the other pane, keeping it on the same byte.
+    fn switch_pane(&mut self) {
+        self.pane = match self.pane {
+            Pane::Hex => Pane::Text,
+            Pane::Text => Pane::Hex,
+        };
+        self.digit = 0;
+    }
+
+    /// Number of digits of a cell in the hex pane.
+    fn cell_digits(&self) -> usize {
+        self.radix.digits(self.word_size)
+    }
+
+    /// Number of chars taken by a cell in the hex pane: its digits and a
+    /// space.
+    fn cell_width(&self) -> usize {
+        self.cell_digits() + 1
+    }
+
+    /// Value of the cell starting at `start`.
+    ///
+    /// Like `od`, bytes missing from a word at the end of the content count
+    /// as zeros.
+    fn cell_value(&self, start: usize) -> u64 {
+        let end = min(start + self.word_size, self.content.len());
+        let mut bytes = self.content.read(start, end).into_owned();
+        bytes.resize(self.word_size, 0);
+        self.endian.read(&bytes)
+    }
+
+    /// Digits of the cell starting at `start`.
+    fn format_cell(&self, start: usize) -> String {
+        self.radix.format(self.cell_value(start), self.word_size)
+    }
+
+    /// Column where the hex cell holding the byte at `col` starts.
+    fn hex_column(&self, col: usize) -> usize {
+        // Groups are separated by an extra space.
+        let cell = col / self.word_size;
+        let separators = if self.group > 1 { cell / self.group } else { 0 };
+        self.address_width()
+            + ADDRESS_PADDING
+            + cell * self.cell_width()
+            + separators
+    }
+
+    /// First byte of the cell of a row which is at column `x`.
+    fn col_at_column(&self, x: usize) -> usize {
+        (0..self.bytes_per_line)
+            .step_by(self.word_size)
+            .rev()
+            .find(|&col| self.hex_column(col) <= x)
+            .unwrap_or(0)
+    }
+
+    /// Column where the text pane starts, with `bytes` bytes on each row.
+    fn text_column_for(&self, bytes: usize) -> usize {
+        // One extra space separates the text from the last hex cell.
+        self.hex_column(bytes - 1) + self.cell_width() + 1
+    }
+
+    /// Column where the text pane starts.
+    fn text_column(&self) -> usize {
+        self.text_column_for(self.bytes_per_line)
+    }
+
+    /// Column of the cursor within its row.
+    fn cursor_column(&self) -> usize {
+        match self.pane {
+            Pane::Hex => self.hex_column(self.selected_col()) + self.digit,
+            Pane::Text => self.text_column() + self.selected_col(),
+        }
+    }
+
+    /// Number of bytes shown on each row, given the width of the view.
+    fn fit_bytes(&self, width: usize) -> usize {
+        // Each byte takes part of a hex cell and a character in the text
+        // pane, and rows hold whole cells.
+        let size = self.word_size;
+        let mut fit = size;
+        while self.text_column_for(fit + size) + fit + size <= width {
+            fit += size;
+        }
+
+        let group = self.group * size;
+        match self.columns {
+            Some(columns) if columns <= fit => {
+                max(columns - columns % size, size)
+            }
+            // Whole groups keep rows aligned.
+            _ if self.group > 1 && fit >= group => fit - fit % group,
+            _ => fit,
+        }
+    }
+
+    /// Prints a byte of the row starting at `start` in both panes.
+    ///
+    /// The whole cell holding the byte is printed in the hex pane.
+    fn draw_byte(&self, printer: &Printer<'_, '_>, start: usize, col: usize) {
+        let cell = self.cell_start(start + col);
+        printer.print((self.hex_column(col), 0), &self.format_cell(cell));
+        printer.print(
+            (self.text_column() + col, 0),
+            &to_printable(self.content[start + col]).to_string(),
+        );
+    }
+
+    fn is_cache_valid(&self, size: Vec2) -> bool {
+        match self.size_cache {
+            None => false,
+            Some(ref last) => last.x.accept(size.x) && last.y.accept(size.y),
+        }
+    }
+
+    fn soft_compute_rows(&mut self, size: Vec2) {
+        if self.is_cache_valid(size) {
+            debug!("Cache is still valid.");
+            return;
+        }
+        debug!("Computing! Oh yeah!");
+
+        self.bytes_per_line = self.fit_bytes(size.x);
+
+        // One line is kept for the editor status.
+        if self.row_count() + 1 > size.y {
+            // Apparently we'll need a scrollbar. Doh :(
+            self.bytes_per_line = self.fit_bytes(size.x.saturating_sub(1));
+        }
+
+        self.size_cache = Some(SizeCache::build(size, size));
+    }
+
+    fn compute_rows(&mut self, size: Vec2) {
+        self.soft_compute_rows(size);
+
+        // Subtracting 1 from size.y so that we have room to display editor
+        // status below the editing area.
+        self.scrollbase
+            .set_heights(size.y.saturating_sub(1), self.row_count());
+    }
+
+    /// Applies an edit to the content, without recording it.
+    ///
+    /// Returns `false`, leaving the content untouched, if the bytes to
+    /// change could not be read from the file.
+    fn apply(&mut self, edit: &Edit) -> bool {
+        let result = match edit {
+            Edit::Insert { offset, bytes } => {
+                self.content.insert(*offset, bytes)
+            }
+            Edit::Remove { offset, bytes, .. } => {
+                self.content.remove(*offset, *offset + bytes.len())
+            }
+            Edit::Replace { offset, new, .. } => {
+                self.content.write(*offset, new)
+            }
+        };
+        if let Err(why) = result {
+            self.set_error(format!("Couldn't read from file: {}", why));
+            return false;
+        }
+
+        self.changes.apply(edit);
+        self.marks.apply(edit);
+        self.jumps.apply(edit);
+        self.revision = self.revision.wrapping_add(1);
+
+        // The number of rows may have changed, which can in turn change
+        // whether we need a scrollbar.
+        self.invalidate();
+        true
+    }
+
+    /// Applies an edit to the content and records it in the history.
+    ///
+    /// Returns `false` if the edit could not be applied.
+    fn edit(&mut self, edit: Edit) -> bool {
+        let applied = self.apply(&edit);
+        if applied {
+            self.history.record(edit);
+        }
+        applied
+    }
+
+    /// Overwrites the bytes from `offset` with `new`, as a single edit.
+    ///
+    /// Nothing is recorded if the bytes are the same.
+    fn replace_at(&mut self, offset: usize, new: Vec<u8>) {
+        let old = self.content.read(offset, offset + new.len()).into_owned();
+        if old != new {
+            self.edit(Edit::Replace { offset, old, new });
+        }
+    }
+
+    /// Overwrites the bytes from `offset` with `bytes`, appending those
+    /// which go past the end of the content.
+    fn write_bytes(&mut self, offset: usize, mut bytes: Vec<u8>) {
+        let overlap = min(bytes.len(), self.content.len() - offset);
+        let appended = bytes.split_off(overlap);
+        self.replace_at(offset, bytes);
+        if !appended.is_empty() {
+            self.edit(Edit::Insert {
+                offset: offset + overlap,
+                bytes: appended,
+            });
+        }
+    }
+
+    fn remove_bytes(&mut self, start: usize, end: usize) {
+        let bytes = self.content.read(start, end).into_owned();
+        self.edit(self.removal(start, bytes));
+    }
+
+    /// Edit removing `bytes` from `offset`, remembering which of them were
+    /// changed.
+    fn removal(&self, offset: usize, bytes: Vec<u8>) -> Edit {
+        Edit::Remove {
+            offset,
+            changed: self.changes.between(offset, offset + bytes.len()),
+            bytes,
+        }
+    }
+
+    /// Writes the cell starting at `start`, appending the bytes which go
+    /// past the end of the content.
+    fn write_cell(&mut self, start: usize, value: u64) {
+        let bytes = self.endian.write(value, self.word_size);
+        self.write_bytes(start, bytes);
+    }
+
+    /// Types a digit in insert mode.
+    ///
+    /// The first digit inserts a new byte or word at the cursor, the
+    /// following ones complete it, the remaining digits being zeros until
+    /// typed.
+    fn insert(&mut self, value: u32) {
+        if self.digit == 0 {
+            match self.radix.with_digit(0, self.word_size, 0, value) {
+                Some(value) => {
+                    self.edit(Edit::Insert {
+                        offset: self.cursor,
+                        bytes: self.endian.write(value, self.word_size),
+                    });
+                }
+                None => return self.reject_digit(),
+            }
+        } else if !self.set_digit(value) {
+            return;
+        }
+        self.next_digit();
+    }
+
+    /// Types a digit in replace mode.
+    ///
+    /// Each digit overwrites a digit of the byte or word under the cursor.
+    /// Bytes are appended when reaching the end of the content.
+    fn overwrite(&mut self, value: u32) {
+        if self.cursor == self.content.len() {
+            self.insert(value);
+        } else if self.set_digit(value) {
+            self.next_digit();
+        }
+    }
+
+    /// Sets the selected digit of the cell under the cursor.
+    ///
+    /// Returns `false` if the cell would go past its largest value, like
+    /// `300` for a byte in decimal.
+    fn set_digit(&mut self, value: u32) -> bool {
+        let old = self.cell_value(self.cursor);
+        match self
+            .radix
+            .with_digit(old, self.word_size, self.digit, value)
+        {
+            Some(new) => {
+                self.write_cell(self.cursor, new);
+                true
+            }
+            None => {
+                self.reject_digit();
+                false
+            }
+        }
+    }
+
+    /// Reports a digit which would make a cell go past its largest value.
+    fn reject_digit(&mut self) {
+        let bits = 8 * self.word_size;
+        self.set_error(format!("Value too large for {} bits", bits));
+    }
+
+    /// Moves on to the next digit once one was typed, or to the next cell
+    /// after the last digit.
+    fn next_digit(&mut self) {
+        if self.digit + 1 < self.cell_digits() {
+            self.digit += 1;
+        } else {
+            self.cursor += self.word_size;
+            self.digit = 0;
+        }
+    }
+
+    /// Types a character in insert mode, in the text pane.
+    ///
+    /// The character is encoded as UTF-8 and inserted before the cursor.
+    fn insert_char(&mut self, ch: char) {
+        let mut buf = [0; 4];
+        let bytes = ch.encode_utf8(&mut buf).as_bytes().to_vec();
+        let len = bytes.len();
+        self.edit(Edit::Insert {
+            offset: self.cursor,
+            bytes,
+        });
+        self.cursor += len;
+    }
+
+    /// Types a character in replace mode, in the text pane.
+    ///
+    /// The character is encoded as UTF-8 and overwrites the bytes under the
+    /// cursor. Bytes are appended when reaching the end of the content.
+    fn overwrite_char(&mut self, ch: char) {
+        let mut buf = [0; 4];
+        let bytes = ch.encode_utf8(&mut buf).as_bytes().to_vec();
+        let len = bytes.len();
+        self.write_bytes(self.cursor, bytes);
+        self.cursor += len;
+    }
+
+    /// Deletes the byte, or word in the hex pane, before the cursor in
+    /// insert mode.
+    ///
+    /// If a byte or word is only partly typed, deletes it instead.
+    fn backspace(&mut self) {
+        if self.digit > 0 {
+            self.remove_bytes(self.cursor, self.cursor + self.word_size);
+            self.digit = 0;
+        } else if self.cursor > 0 {
+            let end = self.cursor;
+            self.cursor = end.saturating_sub(self.step());
+            self.remove_bytes(self.cursor, end);
+        }
+    }
+
+    /// Copies the bytes between `start` and `end` into the selected
+    /// register.
+    fn yank(&mut self, start: usize, end: usize) {
+        let bytes = self.content.read(start, end).into_owned();
+        self.registers.store(self.register_name.take(), bytes);
+        self.set_cursor(start);
+
+        if end - start > 1 {
+            self.set_status(format!("{} bytes yanked", end - start));
+        }
+    }
+
+    /// Removes the bytes between `start` and `end`, keeping them in the
+    /// selected register.
+    fn delete(&mut self, start: usize, end: usize) {
+        let name = self.register_name.take();
+        let bytes = self.content.read(start, end).into_owned();
+        if !self.edit(self.removal(start, bytes.clone())) {
+            return;
+        }
+        self.registers.store(name, bytes);
+
+        self.cursor = min(start, self.last_offset());
+        self.digit = 0;
+    }
+
+    /// Writes the content of the selected register at the cursor, `count`
+    /// times, as a single change.
+    ///
+    /// The content under the cursor is overwritten, unless `insert` is
+    /// `true`.
+    fn put(&mut self, insert: bool, count: Option<usize>) {
+        let name = self.register_name.take();
+        let times = count.unwrap_or(1);
+        let bytes = match self.registers.get(name) {
+            Some(bytes) => match bytes.len().checked_mul(times) {
+                Some(len) if len <= MAX_PUT_LEN => bytes.repeat(times),
+                _ => {
+                    self.set_error("Count too large to put the register");
+                    return;
+                }
+            },
+            None => {
+                let name = name.unwrap_or(UNNAMED);
+                self.set_error(format!("Nothing in register {}", name));
+                return;
+            }
+        };
+
+        let offset = self.cursor;
+        let len = bytes.len();
+        self.history.begin();
+        if insert {
+            self.edit(Edit::Insert { offset, bytes });
+        } else {
+            self.write_bytes(offset, bytes);
+        }
+        self.history.commit();
+
+        // Like Vim, the cursor ends up on the last byte put.
+        self.set_cursor(offset + len - 1);
+    }
+
+    /// Lists the registers holding bytes, with their content.
+    ///
+    /// The unnamed register is named `"`.
+    pub fn registers(&self) -> impl Iterator<Item = (char, &[u8])> {
+        self.registers.iter()
+    }
+
+    /// Range of bytes covered by a motion typed after an operator such as
+    /// `y`, end excluded.
+    ///
+    /// Operators work on whole bytes, even in the hex pane: `y16l` yanks 16
+    /// bytes. Vertical motions, `'` marks, and repeating the operator as in
+    /// `yy`, cover whole rows. Returns `None` if the motion is unknown or
+    /// fails.
+    fn operator_range(
+        &mut self,
+        operator: char,
+        motion: &str,
+        count: Option<usize>,
+    ) -> Option<(usize, usize)> {
+        if self.content.is_empty() {
+            return None;
+        }
+
+        let (len, bytes_per_line) = (self.content.len(), self.bytes_per_line);
+        // Counts past the length of the content cover it all anyway, and
+        // clamping them keeps the arithmetic below from overflowing.
+        let times = min(count.unwrap_or(1), len);
+        let cursor = self.cursor;
+        // Byte motions cover whole words in the hex pane.
+        let (start, step) = (self.cursor_start(), self.step());
+        let row = self.selected_row();
+        let row_start = cursor - self.selected_col();
+        let rows = |first: usize, last: usize| {
+            (
+                first * bytes_per_line,
+                min((last + 1).saturating_mul(bytes_per_line), len),
+            )
+        };
+
+        let range = match motion {
+            "l" | "w" => (start, min(start + times.saturating_mul(step), len)),
+            "h" | "b" if start > 0 => {
+                (start.saturating_sub(times.saturating_mul(step)), start)
+            }
+            "0" if start > row_start => (row_start, start),
+            "$" => {
+                let (_, end) = rows(row, row + times - 1);
+                (start, end)
+            }
+            _ if motion.len() == 1 && motion.starts_with(operator) => {
+                rows(row, row + times - 1)
+            }
+            "j" | "k" | "G" | "gg" | "H" | "M" | "L" => {
+                let key = motion.chars().next().unwrap_or_default();
+                if !self.motion(key, count) {
+                    return None;
+                }
+                let target = self.selected_row();
+                self.cursor = cursor;
+                rows(min(row, target), max(row, target))
+            }
+            // Like Vim, `` `a `` stops right before the mark.
+            _ if motion.starts_with('`') => {
+                let mark =
+                    min(self.marks.get(motion[1..].chars().next()?)?, len - 1);
+                if mark == cursor {
+                    return None;
+                }
+                (min(cursor, mark), max(cursor, mark))
+            }
+            _ if motion.starts_with('\'') => {
+                let mark =
+                    min(self.marks.get(motion[1..].chars().next()?)?, len - 1);
+                let target = mark / bytes_per_line;
+                rows(min(row, target), max(row, target))
+            }
+            _ => return None,
+        };
+
+        Some(range)
+    }
+
+    /// Reverts the last change.
+    ///
+    /// Returns `false` if there was nothing to undo, or if the change could
+    /// not be undone because the file could not be read.
+    pub fn undo(&mut self) -> bool {
+        let change = match self.history.undo() {
+            Some(change) => change,
+            None => return false,
+        };
+
+        // Bytes removed before the last save are new to the saved content
+        // once put back, so they stay marked as inserted.
+        let restore = !self.history.predates_save(&change);
+        let mut undone = 0;
+        for edit in change.edits.iter().rev() {
+            if !self.apply(&edit.inverse()) {
+                break;
+            }
+            if restore {
+                self.changes.restore(edit);
+            }
+            undone += 1;
+        }
+
+        // The edits undone so far are made again, so that the content
+        // still matches the history.
+        let kept = change.edits.len() - undone;
+        if kept > 0 {
+            for edit in &change.edits[kept..] {
+                self.apply(edit);
+            }
+            self.history.redo();
+            return false;
+        }
+
+        self.cursor = min(change.cursor(), self.last_offset());
+        self.digit = 0;
+
+        // Undoing a removal inserts bytes that were there all along.
+        if !self.is_modified() {
+            self.changes.clear();
+        }
+
+        true
+    }
+
+    /// Re-applies the last undone change.
+    ///
+    /// Returns `false` if there was nothing to redo, or if the change could
+    /// not be redone because the file could not be read.
+    pub fn redo(&mut self) -> bool {
+        let change = match self.history.redo() {
+            Some(change) => change,
+            None => return false,
+        };
+
+        let redone = change
+            .edits
+            .iter()
+            .take_while(|edit| self.apply(edit))
+            .count();
+        if redone < change.edits.len() {
+            let restore = !self.history.predates_save(&change);
+            for edit in change.edits[..redone].iter().rev() {
+                self.apply(&edit.inverse());
+                if restore {
+                    self.changes.restore(edit);
+                }
+            }
+            self.history.undo();
+            return false;
+        }
+
+        self.cursor = min(change.cursor(), self.last_offset());
+        self.digit = 0;
+
+        true
+    }
+
+    /// Overwrites the bytes starting at the cursor with `bytes`, as a single
+    /// change. The cursor does not move.
+    ///
+    /// Returns `false`, leaving the content untouched, if there are not
+    /// enough bytes after the cursor.
+    pub fn write_at_cursor(&mut self, bytes: &[u8]) -> bool {
+        let start = self.cursor;
+        if self.content.len().saturating_sub(start) < bytes.len() {
+            return false;
+        }
+
+        self.replace_at(start, bytes.to_vec());
+        true
+    }
+
+    /// Restores the original value of the byte under the cursor.
+    ///
+    /// An inserted byte is removed instead. Returns `false` if the byte was
+    /// not changed since the content was last saved.
+    pub fn revert_byte(&mut self) -> bool {
+        self.revert(self.cursor, self.cursor + 1)
+    }
+
+    /// Restores the whole run of changed bytes around the cursor.
+    ///
+    /// Returns `false` if the byte under the cursor was not changed since
+    /// the content was last saved.
+    pub fn revert_region(&mut self) -> bool {
+        match self.changes.region_at(self.cursor) {
+            Some((start, end)) => self.revert(start, end),
+            None => false,
+        }
+    }
+
+    /// Reverts the changed bytes between `start` and `end`, as a single
+    /// change.
+    fn revert(&mut self, start: usize, end: usize) -> bool {
+        let changed = self.changes.between(start, end);
+        if changed.is_empty() {
+            return false;
+        }
+
+        self.history.begin();
+        // Going backward keeps the offsets valid while removing bytes.
+        for (position, original) in changed.into_iter().rev() {
+            let offset = start + position;
+            match original {
+                Original::Replaced(bytes) => self.replace_at(offset, bytes),
+                Original::Inserted(len) => {
+                    self.remove_bytes(offset, offset + len)
+                }
+            }
+        }
+        self.history.commit();
+
+        self.set_cursor(min(start, self.last_offset()));
+        true
+    }
+
+    /// Moves the cursor to the start of the next run of changed bytes.
+    ///
+    /// When `backward` is `true`, moves to the previous one instead.
+    fn goto_change(&mut self, backward: bool) {
+        let found = if backward {
+            self.changes.previous_region(self.cursor)
+        } else {
+            self.changes.next_region(self.cursor)
+        };
+
+        match found {
+            Some(offset) => self.set_cursor(offset),
+            None if backward => self.set_error("No previous change"),
+            None => self.set_error("No next change"),
+        }
+    }
+
+    /// Enters insert or replace mode.
+    fn enter_edit_mode(&mut self, mode: EditorMode) {
+        self.mode = mode;
+        self.cursor = self.cursor_start();
+        self.digit = 0;
+
+        // Everything typed until we leave the mode is undone at once.
+        self.history.begin();
+    }
+
+    /// Goes back to normal mode from insert or replace mode.
+    fn leave_edit_mode(&mut self) {
+        self.history.commit();
+
+        // Like Vim, step back onto the last inserted byte or word.
+        if self.digit == 0 {
+            self.cursor = self.cursor.saturating_sub(self.step());
+        }
+
+        self.mode = EditorMode::Normal;
+        self.digit = 0;
+        self.cursor = min(self.cursor, self.last_offset());
+    }
+
+    /// Range of bytes currently selected, end excluded.
+    ///
+    /// In visual line mode, the selection covers whole rows.
+    fn selection(&self) -> (usize, usize) {
+        let first = min(self.visual_anchor, self.cursor);
+        let last = max(self.visual_anchor, self.cursor);
+
+        if self.mode == EditorMode::VisualLine {
+            let start = first - self.col_at(first);
+            let end = (self.row_at(last) + 1) * self.bytes_per_line;
+            (start, min(end, self.content.len()))
+        } else {
+            (first, min(last + 1, self.content.len()))
+        }
+    }
+
+    /// Returns the bytes covered by the last visual selection.
+    pub fn last_selection(&self) -> Option<Cow<'_, [u8]>> {
+        self.last_selection.map(|(start, end)| {
+            let end = min(end, self.content.len());
+            self.content.read(min(start, end), end)
+        })
+    }
+
+    /// Starts selecting bytes, or whole rows when `mode` is `VisualLine`.
+    ///
+    /// Pressing the key of the current visual mode again leaves it.
+    fn toggle_visual_mode(&mut self, mode: EditorMode) {
+        if self.mode == mode {
+            self.leave_visual_mode();
+        } else if !self.content.is_empty() {
+            if !self.mode.is_visual() {
+                self.visual_anchor = self.cursor;
+            }
+            self.mode = mode;
+            self.digit = 0;
+        }
+    }
+
+    fn leave_visual_mode(&mut self) {
+        self.last_selection = Some(self.selection());
+        self.mode = EditorMode::Normal;
+    }
+
+    /// Copies the selected bytes into the selected register.
+    fn yank_selection(&mut self) {
+        let (start, end) = self.selection();
+        self.leave_visual_mode();
+        self.yank(start, end);
+    }
+
+    /// Removes the selected bytes, keeping them in the selected register.
+    fn delete_selection(&mut self) {
+        let (start, end) = self.selection();
+        self.leave_visual_mode();
+        self.set_cursor(start);
+        self.perform(Action::DeleteBytes { len: end - start });
+    }
+
+    /// Overwrites the selection with `pattern`, repeated as many times as
+    /// needed.
+    fn fill_selection(&mut self, pattern: Vec<u8>) {
+        let (start, end) = self.selection();
+        self.leave_visual_mode();
+        self.set_cursor(start);
+        self.perform(Action::Fill {
+            pattern,
+            len: end - start,
+        });
+    }
+
+    /// Overwrites the bytes between `start` and `end` with `pattern`,
+    /// repeated as many times as needed, as a single change.
+    fn fill(&mut self, start: usize, end: usize, pattern: &[u8]) {
+        let bytes = pattern.iter().cycle().take(end - start).cloned();
+        self.replace_at(start, bytes.collect());
+        self.set_cursor(start);
+    }
+
+    /// Makes a change at the cursor, remembering it for `.`.
+    fn perform(&mut self, action: Action) {
+        // Repeated changes from visual mode stop at the end of the content.
+        let start = self.cursor;
+        let end = |len: usize| min(start + len, self.content.len());
+
+        match action {
+            Action::Replace { ref bytes, count } => {
+                self.replace_bytes(bytes, count)
+            }
+            Action::Delete { ref motion, count } => {
+                match self.operator_range('d', motion, count) {
+                    Some((start, end)) => self.delete(start, end),
+                    None => self.failed = true,
+                }
+            }
+            Action::Put {
+                register,
+                insert,
+                count,
+            } => {
+                self.register_name = register;
+                self.put(insert, count);
+            }
+            Action::DeleteBytes { len } if start < self.content.len() => {
+                let end = end(len);
+                self.delete(start, end);
+            }
+            Action::Fill { ref pattern, len }
+                if start < self.content.len() =>
+            {
+                let end = end(len);
+                self.fill(start, end, pattern);
+            }
+            Action::DeleteBytes { .. } | Action::Fill { .. } => {
+                self.failed = true
+            }
+        }
+
+        self.last_change = Some(action);
+    }
+
+    /// Repeats the last change at the cursor.
+    ///
+    /// A count replaces the one the change was made with.
+    fn repeat_last_change(&mut self, count: Option<usize>) {
+        let action = match self.last_change.clone() {
+            Some(action) => action,
+            None => return,
+        };
+        let action = match count {
+            Some(count) => action.with_count(count),
+            None => action,
+        };
+
+        // Like Vim, a register given before `.` is used instead.
+        let action = match (action, self.register_name.take()) {
+            (Action::Put { insert, count, .. }, Some(register)) => {
+                Action::Put {
+                    register: Some(register),
+                    insert,
+                    count,
+                }
+            }
+            (action, _) => action,
+        };
+
+        self.perform(action);
+    }
+
+    /// Overwrites the bytes under the cursor with `bytes`, repeated `times`
+    /// times, as a single change.
+    ///
+    /// Like Vim, nothing is replaced if the content is too short.
+    fn replace_bytes(&mut self, bytes: &[u8], times: usize) {
+        let len = bytes.len().saturating_mul(times);
+        if len == 0 || self.content.len() - self.cursor < len {
+            self.failed = true;
+            return;
+        }
+
+        let start = self.cursor;
+        self.replace_at(
+            start,
+            bytes.iter().cycle().take(len).cloned().collect(),
+        );
+
+        // The cursor ends up on the last replaced byte.
+        self.set_cursor(start + len - 1);
+    }
+
+    /// Handles keys which act on the selection in visual modes.
+    ///
+    /// Returns `false` for keys which act the same as in normal mode, such
+    /// as motions.
+    fn handle_visual_input(&mut self, ch: char) -> bool {
+        match ch {
+            'v' => self.toggle_visual_mode(EditorMode::Visual),
+            'V' => self.toggle_visual_mode(EditorMode::VisualLine),
+            // Jump to the other end of the selection
+            'o' => {
+                mem::swap(&mut self.cursor, &mut self.visual_anchor);
+            }
+            'y' => self.yank_selection(),
+            'd' | 'x' | 'X' => self.delete_selection(),
+            'r' => self.pending.push(ch),
+            ':' => {
+                // Like Vim, the command applies to the selection.
+                self.leave_visual_mode();
+                self.mode = EditorMode::Command;
+                self.command = "'<,'>".to_string();
+            }
+            'i' | 'a' | 'u' | 'p' | 'P' => (),
+            _ => return false,
+        }
+        self.count = None;
+        true
+    }
+
+    /// Runs a multi-key command once all its keys have been typed.
+    ///
+    /// Returns `false` if more keys are expected.
+    fn run_pending(&mut self, keys: &str) -> bool {
+        let count = self.count;
+        match keys {
+            "gg" => {
+                self.jump_motion('g', count);
+            }
+            // Register for the next command, which still takes the count
+            _ if keys.starts_with('"') => {
+                let name = keys.chars().nth(1).unwrap_or_default();
+                if Registers::is_valid_name(name) {
+                    self.register_name = Some(name);
+                    self.pending.clear();
+                    return false;
+                }
+            }
+            // Operator followed by a motion, which may have its own count
+            _ if keys.starts_with('y') || keys.starts_with('d') => {
+                let operator = keys.chars().next().unwrap_or_default();
+                let (motion_count, motion) = split_count(&keys[1..]);
+                if motion.is_empty() || ["g", "'", "`"].contains(&motion) {
+                    return false;
+                }
+
+                let count = match (count, motion_count) {
+                    (Some(count), Some(motion_count)) => {
+                        Some(count.saturating_mul(motion_count))
+                    }
+                    (count, motion_count) => count.or(motion_count),
+                };
+                if operator == 'd' {
+                    self.perform(Action::Delete {
+                        motion: motion.to_string(),
+                        count,
+                    });
+                } else if let Some((start, end)) =
+                    self.operator_range(operator, motion, count)
+                {
+                    self.yank(start, end);
+                } else {
+                    self.failed = true;
+                }
+            }
+            _ if keys.starts_with('m') => {
+                let name = keys.chars().nth(1).unwrap_or_default();
+                if Marks::is_valid_name(name) {
+                    self.marks.set(name, self.cursor);
+                } else {
+                    self.failed = true;
+                }
+            }
+            // `'a` goes to the start of the row holding the mark, `` `a ``
+            // to the marked byte itself.
+            _ if keys.starts_with('\'') || keys.starts_with('`') => {
+                let name = keys.chars().nth(1).unwrap_or_default();
+                self.goto_mark(name, keys.starts_with('\''));
+            }
+            "]c" => self.goto_change(false),
+            "[c" => self.goto_change(true),
+            _ if keys.starts_with('q') => {
+                let name = keys.chars().nth(1).unwrap_or_default();
+                if name.is_ascii_alphabetic() {
+                    self.start_recording(name);
+                }
+            }
+            _ if keys.starts_with('@') => {
+                let name = match keys.chars().nth(1).unwrap_or_default() {
+                    '@' => self.last_macro,
+                    name => Some(name),
+                };
+                match name {
+                    Some(name) => self.play_macro(name, count.unwrap_or(1)),
+                    None => self.set_error("No previously used register"),
+                }
+            }
+            // Replacement typed as all the digits of a byte or word, or as
+            // a character in the text pane
+            _ if keys.starts_with('r') => {
+                let typed = &keys[1..];
+                let base = self.radix.base();
+                let bytes = if self.pane == Pane::Text {
+                    typed.as_bytes().to_vec()
+                } else if !typed.chars().all(|c| c.is_digit(base)) {
+                    return true;
+                } else if typed.len() < self.cell_digits() {
+                    return false;
+                } else {
+                    match self.radix.parse(typed, self.word_size) {
+                        Some(value) => {
+                            self.cursor = self.cursor_start();
+                            self.endian.write(value, self.word_size)
+                        }
+                        None => {
+                            self.reject_digit();
+                            self.failed = true;
+                            return true;
+                        }
+                    }
+                };
+
+                if self.mode.is_visual() {
+                    self.fill_selection(bytes);
+                } else {
+                    self.perform(Action::Replace {
+                        bytes,
+                        count: count.unwrap_or(1),
+                    });
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// Handles keys typed in normal and visual modes.
+    fn handle_normal_input(&mut self, ch: char) {
+        if !self.pending.is_empty() {
+            self.pending.push(ch);
+            let keys = self.pending.clone();
+            if self.run_pending(&keys) {
+                self.pending.clear();
+                self.count = None;
+            }
+            return;
+        }
+
+        // A count is typed before the command it applies to, like in `16l`.
+        if let Some(digit) = ch.to_digit(10) {
+            if digit != 0 || self.count.is_some() {
+                let count = self.count.unwrap_or(0);
+                self.count = Some(
+                    count.saturating_mul(10).saturating_add(digit as usize),
+                );
+                return;
+            }
+        }
+
+        // Changes are refused before waiting for the rest of their keys.
+        if self.readonly && CHANGE_KEYS.contains(ch) {
+            self.count = None;
+            self.set_error("Read-only mode");
+            return;
+        }
+
+        if self.mode.is_visual() && self.handle_visual_input(ch) {
+            return;
+        }
+
+        let count = self.count.take();
+        match ch {
+            'v' => self.toggle_visual_mode(EditorMode::Visual),
+            'V' => self.toggle_visual_mode(EditorMode::VisualLine),
+            'i' => self.enter_edit_mode(EditorMode::Insert),
+            'R' => self.enter_edit_mode(EditorMode::Replace),
+            'a' => {
+                // Appending after the whole word under the cursor
+                self.enter_edit_mode(EditorMode::Insert);
+                self.cursor =
+                    min(self.cursor + self.step(), self.content.len());
+            }
+            // Shortcuts for `dl` and `dh`
+            'x' => self.perform(Action::Delete {
+                motion: "l".to_string(),
+                count,
+            }),
+            'X' => self.perform(Action::Delete {
+                motion: "h".to_string(),
+                count,
+            }),
+            'p' | 'P' => self.perform(Action::Put {
+                register: self.register_name,
+                insert: ch == 'P',
+                count,
+            }),
+            '.' => self.repeat_last_change(count),
+            'u' => {
+                self.undo();
+            }
+            ':' => {
+                self.mode = EditorMode::Command;
+                self.command.clear();
+            }
+            'n' => self.search_next(false),
+            'N' => self.search_next(true),
+            'q' if self.recording.is_some() => self.stop_recording(),
+            'g' | ']' | '[' | '"' | 'y' | 'd' | 'r' | 'q' | '@' | 'm'
+            | '\'' | '`' => {
+                // The count applies to the whole multi-key command.
+                self.count = count;
+                self.pending.push(ch);
+            }
+            'G' | 'H' | 'M' | 'L' => {
+                if !self.jump_motion(ch, count) {
+                    self.failed = true;
+                }
+            }
+            _ => {
+                if !self.motion(ch, count) {
+                    self.failed = true;
+                }
+            }
+        }
+    }
+
+    /// Starts recording events into a register.
+    ///
+    /// Like with yanks, an uppercase name appends to the register.
+    fn start_recording(&mut self, name: char) {
+        let events = if name.is_ascii_uppercase() {
+            let name = name.to_ascii_lowercase();
+            self.macros.get(&name).cloned().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        self.recording = Some((name.to_ascii_lowercase(), events));
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some((name, mut events)) = self.recording.take() {
+            // The `q` stopping the recording was recorded as well.
+            events.pop();
+            self.macros.insert(name, events);
+        }
+    }
+
+    /// Queues the events recorded in a register, `count` times.
+    fn play_macro(&mut self, name: char, count: usize) {
+        let events = match self.macros.get(&name.to_ascii_lowercase()) {
+            Some(events) if !events.is_empty() => events,
+            _ => {
+                self.set_error(format!(
+                    "Nothing recorded in register {}",
+                    name
+                ));
+                return;
+            }
+        };
+
+        // Queued in front, so that a macro may play another one.
+        let repeated =
+            min(events.len().saturating_mul(count), MAX_PLAYED_EVENTS + 1);
+        let queued: Vec<Event> =
+            events.iter().cycle().take(repeated).cloned().collect();
+        for event in queued.into_iter().rev() {
+            self.playback.push_front(event);
+        }
+        self.last_macro = Some(name);
+    }
+
+    /// Replays the events queued by macros, until one of them fails.
+    ///
+    /// Returns `result` combined with the results of the replayed events.
+    fn play_queued(&mut self, mut result: EventResult) -> EventResult {
+        self.playing = true;
+
+        let mut played_events = 0;
+        while let Some(event) = self.playback.pop_front() {
+            played_events += 1;
+            if played_events > MAX_PLAYED_EVENTS {
+                self.playback.clear();
+                self.set_error(format!(
+                    "Macro stopped after {} keys",
+                    MAX_PLAYED_EVENTS
+                ));
+                break;
+            }
+
+            self.failed = false;
+            let played = self.handle_event(event);
+            if self.failed || !played.is_consumed() {
+                self.playback.clear();
+            }
+            result = result.and(played);
+        }
+
+        self.playing = false;
+        result
+    }
+
+    /// Moves the cursor according to a motion key, repeated `count` times.
+    ///
+    /// `g` stands for `gg`. Returns `false` if `ch` is not a motion, or if
+    /// the cursor could not move at all.
+    fn motion(&mut self, ch: char, count: Option<usize>) -> bool {
+        let times = count.unwrap_or(1);
+        match ch {
+            'l' => repeat(times, || self.move_right()),
+            'h' => repeat(times, || self.move_left()),
+            'j' => repeat(times, || self.move_down()),
+            'k' => repeat(times, || self.move_up()),
+            'w' => repeat(times, || self.next_byte()),
+            'b' => repeat(times, || self.previous_byte()),
+            '0' => {
+                // Go to start of line
+                self.cursor -= self.selected_col();
+                self.digit = 0;
+                true
+            }
+            '$' => {
+                // Go to end of line, `count - 1` lines below
+                repeat(times - 1, || self.move_down());
+                let row_end = self.cursor - self.selected_col()
+                    + self.bytes_per_line
+                    - 1;
+                self.cursor = min(row_end, self.last_offset());
+                self.digit = self.last_digit();
+                true
+            }
+            // Like Vim, a count selects a row: `5G` goes to the fifth one.
+            'g' | 'G' if count.is_some() => {
+                self.move_to_row(times - 1);
+                self.cursor -= self.selected_col();
+                self.digit = 0;
+                true
+            }
+            'g' => {
+                self.cursor = 0;
+                self.digit = 0;
+                true
+            }
+            'G' => {
+                self.cursor = self.last_offset();
+                self.digit = 0;
+                true
+            }
+            // Top, middle and bottom rows of the view
+            'H' => {
+                let top = self.scrollbase.start_line;
+                let offset = min(times - 1, self.page_height() - 1);
+                self.move_to_row(top + offset);
+                true
+            }
+            'M' => {
+                let top = self.scrollbase.start_line;
+                let visible = min(self.page_height(), self.row_count() - top);
+                self.move_to_row(top + (visible - 1) / 2);
+                true
+            }
+            'L' => {
+                let top = self.scrollbase.start_line;
+                let visible = min(self.page_height(), self.row_count() - top);
+                let offset = min(times - 1, visible - 1);
+                self.move_to_row(top + visible - 1 - offset);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor according to a motion which counts as a jump, such
+    /// as `G`, remembering where it was in the jump list.
+    fn jump_motion(&mut self, ch: char, count: Option<usize>) -> bool {
+        let from = self.cursor;
+        let moved = self.motion(ch, count);
+        if self.cursor != from {
+            self.jumps.push(from);
+        }
+        moved
+    }
+
+    /// Moves the cursor to `offset`, remembering where it was in the jump
+    /// list.
+    fn jump_to(&mut self, offset: usize) {
+        if offset != self.cursor {
+            self.jumps.push(self.cursor);
+        }
+        self.set_cursor(offset);
+    }
+
+    /// Moves the cursor to a mark, or to the start of its row.
+    fn goto_mark(&mut self, name: char, row_start: bool) {
+        let offset = match self.marks.get(name) {
+            Some(offset) => min(offset, self.last_offset()),
+            None => {
+                self.set_error(format!("Mark not set: {}", name));
+                return;
+            }
+        };
+
+        if row_start {
+            self.jump_to(offset - offset % self.bytes_per_line);
+        } else {
+            self.jump_to(offset);
+        }
+    }
+
+    /// Lists the marks which are set, with the offset of their byte.
+    pub fn marks(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.marks.iter()
+    }
+
+    /// Goes `count` positions back in the jump list, or forward.
+    fn browse_jumps(&mut self, forward: bool, count: Option<usize>) {
+        let mut target = None;
+        for _ in 0..count.unwrap_or(1) {
+            let jump = if forward {
+                self.jumps.forward()
+            } else {
+                self.jumps.back(self.cursor)
+            };
+            match jump {
+                Some(offset) => target = Some(offset),
+                None => break,
+            }
+        }
+
+        match target {
+            Some(offset) => self.set_cursor(min(offset, self.last_offset())),
+            None => self.failed = true,
+        }
+    }
+
+    /// Searches for `query` and moves the cursor to the next match.
+    ///
+    /// In hex mode, the query is a sequence of hex bytes such as
+    /// `DE AD ?? EF`, where `??` matches any byte. In text mode, it is
+    /// encoded with the given encoding first.
+    ///
+    /// When `backward` is `true`, looks for the previous match instead. The
+    /// search can then be repeated with `n` and `N`, and every match in
+    /// view is highlighted.
+    pub fn search(&mut self, query: &str, mode: SearchMode, backward: bool) {
+        match Pattern::new(query, mode) {
+            Ok(pattern) => {
+                self.last_search = Some(Search {
+                    query: query.to_string(),
+                    pattern,
+                    backward,
+                });
+                self.search_next(false);
+            }
+            Err(message) => self.set_error(message),
+        }
+    }
+
+    /// Moves the cursor to the next match of the last search.
+    ///
+    /// When `reverse` is `true`, searches in the opposite direction.
+    fn search_next(&mut self, reverse: bool) {
+        let found = match self.last_search {
+            Some(ref search) => search.pattern.find(
+                &self.content,
+                self.cursor,
+                search.backward != reverse,
+            ),
+            None => {
+                self.set_error("No previous search pattern");
+                return;
+            }
+        };
+
+        match found {
+            Some(found) => {
+                if found.wrapped {
+                    self.set_status("Search wrapped around");
+                }
+                self.jump_to(found.offset);
+            }
+            None => {
+                let query = self.last_search.as_ref().map(|s| &s.query[..]);
+                self.set_error(format!(
+                    "Pattern not found: {}",
+                    query.unwrap_or_default()
+                ));
+            }
+        }
+    }
+
+    /// Flags which bytes between `start` and `end` belong to a match of the
+    /// last search.
+    fn search_hits(&self, start: usize, end: usize) -> Vec<bool> {
+        let mut hits = vec![false; end - start];
+
+        if let Some(ref search) = self.last_search {
+            let len = search.pattern.len();
+
+            // Matches starting before `start` may still overlap the range.
+            let first = start.saturating_sub(len - 1);
+            let last = min(end + len - 1, self.content.len());
+            let bytes = self.content.read(first, last);
+            for offset in first..end {
+                if search.pattern.matches_at(&bytes, offset - first) {
+                    let from = offset.saturating_sub(start);
+                    let to = min(offset + len, end) - start;
+                    for hit in &mut hits[from..to] {
+                        *hit = true;
+                    }
+                }
+            }
+        }
+
+        hits
+    }
+
+    /// Handles events while an Ex command is being typed.
+    fn on_command_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char(ch) => self.command.push(ch),
+            Event::Key(Key::Backspace) => {
+                // Like Vim, erasing past the `:` cancels the command.
+                if self.command.pop().is_none() {
+                    self.mode = EditorMode::Normal;
+                }
+            }
+            Event::Key(Key::Esc) => {
+                self.mode = EditorMode::Normal;
+                self.command.clear();
+            }
+            Event::Key(Key::Enter) => {
+                self.mode = EditorMode::Normal;
+                let command = mem::replace(&mut self.command, String::new());
+                if let Some(cb) = self.on_command.clone() {
+                    return EventResult::with_cb(move |s| cb(s, &command));
+                }
+            }
+            _ => return EventResult::Ignored,
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    /// Moves the cursor to the byte at `offset`, remembering where it was
+    /// in the jump list.
+    ///
+    /// Returns `false` if `offset` is past the end of the content.
+    pub fn goto(&mut self, offset: usize) -> bool {
+        if offset >= self.content.len() {
+            return false;
+        }
+
+        self.jump_to(offset);
+        true
+    }
+
+    /// Handles an event, be it typed by the user or replayed by a macro.
+    fn handle_event(&mut self, event: Event) -> EventResult {
+        // Messages only stay until the user does something else.
+        self.status = None;
+
+        if self.mode.is_command() {
+            return self.on_command_event(event);
+        }
+
+        // Counts typed in normal mode apply to the next command, which may
+        // also be a key such as Ctrl-D.
+        let count = match event {
+            Event::Char(_) => None,
+            _ => self.count.take(),
+        };
+
+        let mut fix_scroll = true;
+        match event {
+            // The search prompt is opened by the application.
+            Event::Char('/') | Event::Char('?') if !self.mode.is_editing() => {
+                return EventResult::Ignored;
+            }
+            Event::Char(ch) if !self.mode.is_editing() => {
+                self.handle_normal_input(ch);
+            }
+            Event::Char(ch) if self.pane == Pane::Text => {
+                if self.mode.is_insert() {
+                    self.insert_char(ch);
+                } else {
+                    self.overwrite_char(ch);
+                }
+            }
+            Event::Char(ch) => {
+                // Only type a digit if the input is valid in the radix
+                if let Some(value) = ch.to_digit(self.radix.base()) {
+                    if self.mode.is_insert() {
+                        self.insert(value);
+                    } else {
+                        self.overwrite(value);
+                    }
+                }
+            }
+            Event::Key(Key::Backspace) if self.mode.is_insert() => {
+                self.backspace();
+            }
+            Event::Key(Key::Backspace) if self.mode.is_replace() => {
+                self.move_left();
+            }
+            Event::Key(Key::Tab) => self.switch_pane(),
+
+            Event::Key(Key::Esc) if self.mode.is_editing() => {
+                self.leave_edit_mode();
+            }
+            Event::Key(Key::Esc) => {
+                self.pending.clear();
+                self.count = None;
+                self.register_name = None;
+                if self.mode.is_visual() {
+                    self.leave_visual_mode();
+                }
+            }
+            Event::CtrlChar('r') if self.mode.is_normal() => {
+                if self.readonly {
+                    self.set_error("Read-only mode");
+                } else {
+                    self.redo();
+                }
+            }
+            Event::CtrlChar('o') if !self.mode.is_editing() => {
+                self.browse_jumps(false, count);
+            }
+            // Terminals send Ctrl-I as Tab, which switches panes, so Ctrl-N
+            // goes forward as well.
+            Event::CtrlChar('i') | Event::CtrlChar('n')
+                if !self.mode.is_editing() =>
+            {
+                self.browse_jumps(true, count);
+            }
+            // Half-page scrolling, or by `count` rows like Vim
+            Event::CtrlChar('d') if !self.mode.is_editing() => {
+                let rows = count.unwrap_or(self.page_height() / 2);
+                self.scroll_down(max(1, rows));
+            }
+            Event::CtrlChar('u') if !self.mode.is_editing() => {
+                let rows = count.unwrap_or(self.page_height() / 2);
+                self.scroll_up(max(1, rows));
+            }
+            // Full-page scrolling, keeping two rows of context like Vim
+            Event::CtrlChar('f') | Event::Key(Key::PageDown) => {
+                let page = max(1, self.page_height().saturating_sub(2));
+                self.scroll_down(page.saturating_mul(count.unwrap_or(1)));
+            }
+            Event::CtrlChar('b') | Event::Key(Key::PageUp) => {
+                let page = max(1, self.page_height().saturating_sub(2));
+                self.scroll_up(page.saturating_mul(count.unwrap_or(1)));
+            }
+
+            Event::Ctrl(Key::Home) => {
+                self.cursor = 0;
+                self.digit = 0;
+            }
+            Event::Ctrl(Key::End) => {
+                self.cursor = self.max_cursor();
+                self.digit = self.last_digit();
+            }
+            Event::Key(Key::Up) if self.selected_row() > 0 => {
+                self.move_up();
+            }
+            Event::Key(Key::Down)
+                if self.selected_row() + 1 < self.row_count() =>
+            {
+                self.move_down();
+            }
+            Event::Key(Key::Left) if !self.is_at_start() => {
+                self.move_left();
+            }
+            Event::Key(Key::Right) if !self.is_at_end() => {
+                self.move_right();
+            }
+            Event::Mouse {
+                event: MouseEvent::WheelUp,
+                ..
+            } if self.scrollbase.can_scroll_up() => {
+                fix_scroll = false;
+                self.scrollbase.scroll_up(5);
+            }
+            Event::Mouse {
+                event: MouseEvent::WheelDown,
+                ..
+            } if self.scrollbase.can_scroll_down() => {
+                fix_scroll = false;
+                self.scrollbase.scroll_down(5);
+            }
+            Event::Mouse {
+                event: MouseEvent::Press(MouseButton::Left),
+                position,
+                offset,
+            } if position
+                .checked_sub(offset)
+                .map(|position| {
+                    self.scrollbase.start_drag(position, self.last_size.x)
+                })
+                .unwrap_or(false) =>
+            {
+                fix_scroll = false;
+            }
+            Event::Mouse {
+                event: MouseEvent::Hold(MouseButton::Left),
+                position,
+                offset,
+            } => {
+                fix_scroll = false;
+                let position = position.saturating_sub(offset);
+                self.scrollbase.drag(position);
+            }
+            Event::Mouse {
+                event: MouseEvent::Press(_),
+                position,
+                offset,
+            } if !self.content.is_empty()
+                && position.fits_in_rect(offset, self.last_size) =>
+            {
+                if let Some(position) = position.checked_sub(offset) {
+                    let y = position.y + self.scrollbase.start_line;
+                    let y = min(y, self.row_count() - 1);
+
+                    // Clicking on either pane moves the cursor there.
+                    let col = if position.x >= self.text_column() {
+                        self.pane = Pane::Text;
+                        self.digit = 0;
+                        position.x - self.text_column()
+                    } else {
+                        let col = self.col_at_column(position.x);
+                        let x =
+                            position.x.saturating_sub(self.hex_column(col));
+                        self.pane = Pane::Hex;
+                        self.digit = min(x, self.cell_digits() - 1);
+                        col
+                    };
+                    let col = min(col, self.bytes_per_line - 1);
+
+                    self.cursor =
+                        min(y * self.bytes_per_line + col, self.last_offset());
+                }
+            }
+            _ => return EventResult::Ignored,
+        }
+
+        if fix_scroll {
+            let focus = self.selected_row();
+            self.scrollbase.scroll_to(focus);
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    /// Displays the editor status below the editing area.
+    fn draw_status(&self, printer: &Printer<'_, '_>) {
+        let status_y = printer.size.y - 1;
+        if self.mode.is_command() {
+            let command = format!(":{}", self.command);
+            printer.print((0, status_y), &command);
+            printer.with_effect(Effect::Reverse, |printer| {
+                printer.print((command.width(), status_y), " ");
+            });
+        } else {
+            let mut mode = self.mode.to_string();
+            if self.is_modified() {
+                mode.push_str(" [+]");
+            }
+            if self.readonly {
+                mode.push_str(" [RO]");
+            }
+            if let Some((name, _)) = self.recording {
+                mode.push_str(&format!(" recording @{}", name));
+            }
+            printer.print((0, status_y), &mode);
+
+            // Failing to read the file matters more than any other message.
+            let read_error = self.content.read_error().map(|why| {
+                Status::Error(format!("Couldn't read from file: {}", why))
+            });
+
+            let x = mode.len() + 2;
+            match read_error.as_ref().or(self.status.as_ref()) {
+                Some(Status::Info(ref message)) => {
+                    printer.print((x, status_y), message)
+                }
+                Some(Status::Error(ref message)) => {
+                    let style = ColorStyle::new(
+                        Color::Dark(BaseColor::Red),
+                        PaletteColor::View,
+                    );
+                    printer.with_color(style, |printer| {
+                        printer.print((x, status_y), message)
+                    });
+                }
+                None if self.mode.is_visual() => {
+                    let (start, end) = self.selection();
+                    printer.print(
+                        (x, status_y),
+                        &format!(
+                            "{} bytes selected, {}-{}",
+                            end - start,
+                            self.format_address(start),
+                            self.format_address(end - 1)
+                        ),
+                    );
+                }
+                None => (),
+            }
+        }
+    }
+
+    /// Displays the rows of the content, above the status line.
+    fn draw_rows(&self, printer: &Printer<'_, '_>) {
+        // Cropping printer so that we don't draw over status info
+        let printer = &printer.cropped((printer.size.x, printer.size.y - 1));
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            let effect = if self.enabled && printer.enabled {
+                Effect::Reverse
+            } else {
+                Effect::Simple
+            };
+
+            let w = if self.scrollbase.scrollable() {
+                printer.size.x.saturating_sub(1)
+            } else {
+                printer.size.x
+            };
+            printer.with_effect(effect, |printer| {
+                for y in 0..printer.size.y {
+                    printer.print_hline((0, y), w, " ");
+                }
+            });
+
+            // Only look for search matches in the rows we actually draw.
+            let visible_start =
+                self.scrollbase.start_line * self.bytes_per_line;
+            let visible_end = min(
+                visible_start
+                    + self.scrollbase.view_height * self.bytes_per_line,
+                self.content.len(),
+            );
+            let hits = self.search_hits(visible_start, visible_end);
+            let selection = if self.mode.is_visual() {
+                self.selection()
+            } else {
+                (0, 0)
+            };
+
+            self.scrollbase.draw(printer, |printer, i| {
+                debug!("Drawing row {}", i);
+                let start = i * self.bytes_per_line;
+                let end = min(start + self.bytes_per_line, self.content.len());
+                let bytes = self.content.read(start, end);
+                let address = self.format_address(start);
+                printer.with_effect(effect, |printer| {
+                    printer.print((0, 0), &address);
+                    for col in (0..bytes.len()).step_by(self.word_size) {
+                        printer.print(
+                            (self.hex_column(col), 0),
+                            &self.format_cell(start + col),
+                        );
+                    }
+                    printer.print(
+                        (self.text_column(), 0),
+                        &bytes
+                            .iter()
+                            .map(|&b| to_printable(b))
+                            .collect::<String>(),
+                    );
+                });
+
+                // Unsaved changes stand out, unless they are search matches.
+                printer.with_color(ColorStyle::modified(), |printer| {
+                    for col in 0..bytes.len() {
+                        if !self.changes.contains(start + col) {
+                            continue;
+                        }
+                        self.draw_byte(printer, start, col);
+                    }
+                });
+
+                printer.with_color(ColorStyle::highlight(), |printer| {
+                    for col in 0..bytes.len() {
+                        if !hits[start + col - visible_start] {
+                            continue;
+                        }
+                        self.draw_byte(printer, start, col);
+                    }
+                });
+
+                // The selection is drawn without the reverse effect.
+                for col in 0..bytes.len() {
+                    let offset = start + col;
+                    if selection.0 <= offset && offset < selection.1 {
+                        self.draw_byte(printer, start, col);
+                    }
+                }
+
+                if printer.focused && i == self.selected_row() {
+                    let byte = self.content.get(self.cursor);
+
+                    // Underline the selected byte in the other pane, or all
+                    // the bytes of the selected word.
+                    let col = self.selected_col();
+                    let cell = self.cell_start(self.cursor);
+                    let (mirror_x, mirror) = match self.pane {
+                        Pane::Hex => {
+                            let end =
+                                min(cell + self.word_size, self.content.len());
+                            let text = self
+                                .content
+                                .read(cell, end)
+                                .iter()
+                                .map(|&b| to_printable(b))
+                                .collect::<String>();
+                            (
+                                self.text_column() + col
+                                    - col % self.word_size,
+                                byte.map(|_| text),
+                            )
+                        }
+                        Pane::Text => (
+                            self.hex_column(col),
+                            byte.map(|_| self.format_cell(cell)),
+                        ),
+                    };
+                    // In visual modes, the selection already shows in both
+                    // panes.
+                    let mirror = mirror.filter(|_| !self.mode.is_visual());
+                    if let Some(mirror) = mirror {
+                        printer.with_effect(effect, |printer| {
+                            printer.with_effect(
+                                Effect::Underline,
+                                |printer| {
+                                    printer.print((mirror_x, 0), &mirror);
+                                },
+                            );
+                        });
+                    }
+
+                    let c = match byte {
+                        // Right after the last byte, where new bytes go.
+                        None => '_',
+                        Some(byte) if self.pane == Pane::Text => {
+                            to_printable(byte)
+                        }
+                        Some(_) => self.radix.digit_char(
+                            self.cell_value(cell),
+                            self.word_size,
+                            self.digit,
+                        ),
+                    };
+                    let cursor_effect = if self.mode.is_visual() {
+                        Effect::Underline
+                    } else {
+                        Effect::Simple
+                    };
+                    printer.with_effect(cursor_effect, |printer| {
+                        printer
+                            .print((self.cursor_column(), 0), &c.to_string());
+                    });
+                }
+            });
+        });
+    }
+}
+
+impl View for HexArea {
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        // Make sure our structure is up to date
+        self.soft_compute_rows(constraint);
+
+        // One extra line for the editor status
+        let height = self.row_count() + 1;
+        let scroll_width = if height > constraint.y { 1 } else { 0 };
+        Vec2::new(
+            scroll_width + self.text_column() + self.bytes_per_line,
+            height,
+        )
+    }
+
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        self.draw_rows(printer);
+
+        // Drawn last, so that errors met while reading the rows show too.
+        self.draw_status(printer);
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        if let (Some((_, events)), false) =
+            (self.recording.as_mut(), self.playing)
+        {
+            events.push(event.clone());
+        }
+
+        // Reading the file is tried again for each event.
+        self.content.clear_read_error();
+
+        let before = (self.cursor, self.revision);
+        let mut result = self.handle_event(event);
+        // Keys left to the application, such as `/` opening the search
+        // prompt, would stop the macro when played back.
+        if !result.is_consumed() && !self.playing {
+            if let Some((_, events)) = self.recording.as_mut() {
+                events.pop();
+            }
+        }
+        if !self.playing && !self.playback.is_empty() {
+            result = self.play_queued(result);
+        }
+
+        // Pages of a big file read while handling the event are not all
+        // kept in memory.
+        self.content.trim();
+
+        if (self.cursor, self.revision) != before {
+            if let Some(cb) = self.on_cursor_move.clone() {
+                let cursor = self.cursor;
+                result =
+                    result.and(EventResult::with_cb(move |s| cb(s, cursor)));
+            }
+        }
+        result
+    }
+
+    fn take_focus(&mut self, _: Direction) -> bool {
+        self.enabled
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.last_size = size;
+        self.compute_rows(size);
+    }
+
+    fn important_area(&self, _: Vec2) -> Rect {
+        // The important area is the selected digit or character
+        Rect::from_size((self.cursor_column(), self.selected_row()), (1, 1))
+    }
+}
+
+/// Calls `step` up to `times` times, stopping as soon as it returns `false`.
+///
+/// Returns `true` if at least one step succeeded.
+fn repeat<F: FnMut() -> bool>(times: usize, mut step: F) -> bool {
+    let mut moved = false;
+    for _ in 0..times {
+        if !step() {
+            break;
+        }
+        moved = true;
+    }
+    moved
+}
+
+/// Splits the count typed at the start of `keys`, as in `16l`.
+fn split_count(keys: &str) -> (Option<usize>, &str) {
+    // A leading 0 is the motion to the start of the row.
+    let digits = if keys.starts_with('0') {
+        0
+    } else {
+        keys.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(keys.len())
+    };
+
+    (keys[..digits].parse().ok(), &keys[digits..])
+}
+
+/// Character shown in the text pane for the given byte.
+fn to_printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out a view over `bytes`, wide enough for 16 bytes per row.
+    fn hex_area(bytes: &[u8]) -> HexArea {
+        let mut view = HexArea::from(bytes.to_vec());
+        view.layout(Vec2::new(80, 24));
+        view
+    }
+
+    /// Sends the characters of `keys` one at a time, like typing them.
+    fn type_keys(view: &mut HexArea, keys: &str) {
+        for ch in keys.chars() {
+            view.on_event(Event::Char(ch));
+        }
+    }
+
+    fn press(view: &mut HexArea, key: Key) {
+        view.on_event(Event::Key(key));
+    }
+
+    fn content(view: &HexArea) -> Vec<u8> {
+        view.content.read(0, view.content.len()).into_owned()
+    }
+
+    fn error(view: &HexArea) -> Option<&str> {
+        match view.status {
+            Some(Status::Error(ref message)) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Bytes 0, 1, 2... up to `len`.
+    fn counting(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn inserting_bytes_grows_the_content() {
+        let mut view = hex_area(&counting(15));
+        type_keys(&mut view, "wwi");
+        type_keys(&mut view, "AB");
+        type_keys(&mut view, "C");
+        assert_eq!(view.cursor(), 3);
+        assert_eq!(view.digit, 1);
+        press(&mut view, Key::Esc);
+
+        let mut expected = counting(15);
+        expected.splice(2..2, vec![0xAB, 0xC0]);
+        assert_eq!(content(&view), expected);
+        assert_eq!(view.cursor(), 3);
+        assert_eq!(view.row_count(), 2);
+        assert!(view.is_modified());
+
+        // `a` appends after the last byte.
+        type_keys(&mut view, "Ga01");
+        press(&mut view, Key::Esc);
+        expected.push(0x01);
+        assert_eq!(content(&view), expected);
+        assert_eq!(view.cursor(), 17);
+    }
+
+    #[test]
+    fn removing_bytes_shrinks_the_content() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "wx");
+        assert_eq!(content(&view)[..3], [0, 2, 3]);
+        assert_eq!(view.cursor(), 1);
+
+        type_keys(&mut view, "3x");
+        assert_eq!(content(&view)[..3], [0, 5, 6]);
+
+        type_keys(&mut view, "X");
+        assert_eq!(content(&view)[..3], [5, 6, 7]);
+        assert_eq!(view.cursor(), 0);
+
+        // `dd` removes the whole row, so the last one moves up.
+        type_keys(&mut view, "dd");
+        assert_eq!(view.content.len(), 19);
+        assert_eq!(view.row_count(), 2);
+        assert_eq!(view.get_buffer().get(0), Some(21));
+
+        // The cursor stays on the last byte when removing it.
+        type_keys(&mut view, "Gx");
+        assert_eq!(view.content.len(), 18);
+        assert_eq!(view.cursor(), 17);
+    }
+
+    #[test]
+    fn backspace_removes_the_byte_typed_before_the_cursor() {
+        let mut view = hex_area(&counting(4));
+        type_keys(&mut view, "wwi12");
+        press(&mut view, Key::Backspace);
+        press(&mut view, Key::Backspace);
+        assert_eq!(content(&view), [0, 2, 3]);
+        assert_eq!(view.cursor(), 1);
+
+        // A partly typed byte is removed at once.
+        type_keys(&mut view, "3");
+        press(&mut view, Key::Backspace);
+        press(&mut view, Key::Esc);
+        assert_eq!(content(&view), [0, 2, 3]);
+    }
+
+    #[test]
+    fn address_column_widens_with_the_content() {
+        let mut view = hex_area(&counting(15));
+        view.set_base_address(0xFFFF_FFF0);
+        assert_eq!(view.address_width(), 8);
+        assert_eq!(view.format_address(0), "FFFFFFF0");
+        let text_column = view.text_column();
+
+        // The 16th byte puts the end of the content past 4 GiB, and needs
+        // a new row for a cursor after it.
+        type_keys(&mut view, "Ga00");
+        press(&mut view, Key::Esc);
+        assert_eq!(view.address_width(), 12);
+        assert_eq!(view.format_address(15), "0000FFFFFFFF");
+        assert_eq!(view.text_column(), text_column + 4);
+        assert_eq!(view.row_count(), 2);
+
+        type_keys(&mut view, "x");
+        assert_eq!(view.address_width(), 8);
+        assert_eq!(view.row_count(), 1);
+    }
+
+    #[test]
+    fn counts_repeat_motions() {
+        let mut view = hex_area(&counting(1024));
+        // `l` moves by digit in the hex pane, `w` by byte.
+        type_keys(&mut view, "16l");
+        assert_eq!(view.cursor(), 8);
+        type_keys(&mut view, "3w");
+        assert_eq!(view.cursor(), 11);
+        type_keys(&mut view, "10j");
+        assert_eq!(view.cursor(), 171);
+        type_keys(&mut view, "2k");
+        assert_eq!(view.cursor(), 139);
+
+        // Motions stop at the edges of the content.
+        type_keys(&mut view, "999b");
+        assert_eq!(view.cursor(), 0);
+        type_keys(&mut view, "2$");
+        assert_eq!(view.cursor(), 31);
+    }
+
+    #[test]
+    fn counts_select_rows_for_g_and_capital_g() {
+        let mut view = hex_area(&counting(1024));
+        type_keys(&mut view, "G");
+        assert_eq!(view.cursor(), 1023);
+        type_keys(&mut view, "gg");
+        assert_eq!(view.cursor(), 0);
+
+        type_keys(&mut view, "3l5G");
+        assert_eq!(view.cursor(), 4 * 16);
+        type_keys(&mut view, "2gg");
+        assert_eq!(view.cursor(), 16);
+        type_keys(&mut view, "1000G");
+        assert_eq!(view.cursor(), 63 * 16);
+    }
+
+    #[test]
+    fn h_m_and_l_move_within_the_view() {
+        let mut view = hex_area(&counting(1024));
+        // 23 rows are visible, the last line showing the status.
+        type_keys(&mut view, "wL");
+        assert_eq!(view.cursor(), 22 * 16 + 1);
+        type_keys(&mut view, "3L");
+        assert_eq!(view.cursor(), 20 * 16 + 1);
+        type_keys(&mut view, "M");
+        assert_eq!(view.cursor(), 11 * 16 + 1);
+        type_keys(&mut view, "H");
+        assert_eq!(view.cursor(), 1);
+        type_keys(&mut view, "4H");
+        assert_eq!(view.cursor(), 3 * 16 + 1);
+        type_keys(&mut view, "99H");
+        assert_eq!(view.cursor(), 22 * 16 + 1);
+
+        // Scrolling moves the rows `H` refers to.
+        view.on_event(Event::CtrlChar('f'));
+        type_keys(&mut view, "H");
+        assert_eq!(view.cursor(), 21 * 16 + 1);
+    }
+
+    #[test]
+    fn huge_counts_stop_at_the_end_of_the_content() {
+        let huge = "99999999999999999999999";
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, &format!("{}yy", huge));
+        assert_eq!(view.registers.get(None).map(<[u8]>::len), Some(40));
+        type_keys(&mut view, &format!("w{}d$", huge));
+        assert_eq!(content(&view), [0]);
+        type_keys(&mut view, &format!("u{}dd", huge));
+        assert!(view.content.is_empty());
+
+        let mut view = hex_area(&counting(1024));
+        type_keys(&mut view, huge);
+        view.on_event(Event::CtrlChar('f'));
+        assert_eq!(view.scrollbase.start_line, 65 - 23);
+        type_keys(&mut view, huge);
+        view.on_event(Event::CtrlChar('u'));
+        assert_eq!(view.scrollbase.start_line, 0);
+    }
+
+    #[test]
+    fn visual_mode_selects_bytes_between_the_anchor_and_the_cursor() {
+        let mut view = hex_area(&counting(64));
+        type_keys(&mut view, "4wv3w");
+        assert_eq!(view.mode, EditorMode::Visual);
+        assert_eq!(view.selection(), (4, 8));
+
+        // Going back past the anchor selects bytes before it.
+        type_keys(&mut view, "6b");
+        assert_eq!(view.selection(), (1, 5));
+
+        // `o` swaps the ends of the selection.
+        type_keys(&mut view, "o");
+        assert_eq!(view.cursor(), 4);
+        assert_eq!(view.selection(), (1, 5));
+        type_keys(&mut view, "j");
+        assert_eq!(view.selection(), (1, 21));
+
+        type_keys(&mut view, "y");
+        assert_eq!(view.mode, EditorMode::Normal);
+        assert_eq!(view.cursor(), 1);
+        let selected = &counting(21)[1..];
+        assert_eq!(view.registers.get(None), Some(selected));
+        assert_eq!(view.last_selection().as_deref(), Some(selected));
+    }
+
+    #[test]
+    fn visual_line_mode_selects_whole_rows() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "3wVj");
+        assert_eq!(view.mode, EditorMode::VisualLine);
+        assert_eq!(view.selection(), (0, 32));
+
+        // The last row may be shorter.
+        type_keys(&mut view, "j");
+        assert_eq!(view.selection(), (0, 40));
+        type_keys(&mut view, "oj");
+        assert_eq!(view.selection(), (16, 40));
+
+        // `v` switches to selecting bytes from the same anchor.
+        type_keys(&mut view, "v");
+        assert_eq!(view.selection(), (19, 36));
+
+        type_keys(&mut view, "d");
+        let rest = &counting(40)[36..];
+        assert_eq!(content(&view), [&counting(19)[..], rest].concat());
+        assert_eq!(view.cursor(), 19);
+    }
+
+    #[test]
+    fn escape_and_the_same_key_leave_visual_modes() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "vwV");
+        assert_eq!(view.mode, EditorMode::VisualLine);
+        type_keys(&mut view, "V");
+        assert_eq!(view.mode, EditorMode::Normal);
+        assert_eq!(view.last_selection, Some((0, 16)));
+
+        type_keys(&mut view, "vw");
+        press(&mut view, Key::Esc);
+        assert_eq!(view.mode, EditorMode::Normal);
+        assert_eq!(view.last_selection, Some((1, 3)));
+        assert_eq!(content(&view), counting(40));
+    }
+
+    #[test]
+    fn putting_too_many_bytes_is_refused() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "y4l");
+        type_keys(&mut view, "99999999999999p");
+        assert_eq!(error(&view), Some("Count too large to put the register"));
+        type_keys(&mut view, "99999999999999999999999P");
+        assert!(error(&view).is_some());
+        assert_eq!(content(&view), counting(40));
+        assert!(!view.is_modified());
+
+        // Repeating a put with a new count is refused the same way.
+        type_keys(&mut view, "3P");
+        assert_eq!(view.content.len(), 52);
+        type_keys(&mut view, "99999999999999.");
+        assert!(error(&view).is_some());
+        assert_eq!(view.content.len(), 52);
+    }
+
+    #[test]
+    fn failed_deletes_leave_the_register_alone() {
+        let path = std::env::temp_dir()
+            .join(format!("hex-area-{}-delete", std::process::id()));
+        std::fs::write(&path, vec![1; 0x20000]).unwrap();
+        let mut view = hex_area(&[]);
+        view.set_buffer(Buffer::open(&path).unwrap());
+        type_keys(&mut view, "yl");
+
+        // Another program cuts the file short, so the last page is gone.
+        let file = std::fs::OpenOptions::new().write(true).open(&path);
+        file.unwrap().set_len(1).unwrap();
+        type_keys(&mut view, "Gx");
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(error(&view).is_some());
+        assert_eq!(view.content.len(), 0x20000);
+        assert_eq!(view.registers.get(None), Some(&[1][..]));
+    }
+
+    #[test]
+    fn r_replaces_bytes_under_the_cursor() {
+        let mut view = hex_area(&counting(8));
+        type_keys(&mut view, "wrAB");
+        assert_eq!(content(&view)[..3], [0, 0xAB, 2]);
+        assert_eq!(view.cursor(), 1);
+        assert_eq!(view.mode, EditorMode::Normal);
+
+        // With a count, the cursor ends up on the last replaced byte.
+        type_keys(&mut view, "3rff");
+        assert_eq!(content(&view)[..5], [0, 0xFF, 0xFF, 0xFF, 4]);
+        assert_eq!(view.cursor(), 3);
+
+        // Replacing past the end changes nothing.
+        type_keys(&mut view, "9r00");
+        assert_eq!(content(&view)[..5], [0, 0xFF, 0xFF, 0xFF, 4]);
+
+        // In the text pane, the replacement is a character.
+        press(&mut view, Key::Tab);
+        type_keys(&mut view, "rz");
+        assert_eq!(content(&view)[3], b'z');
+        type_keys(&mut view, "u");
+        assert_eq!(content(&view)[3], 0xFF);
+    }
+
+    #[test]
+    fn replace_mode_overwrites_and_appends() {
+        let mut view = hex_area(&counting(3));
+        type_keys(&mut view, "wR");
+        assert_eq!(view.mode, EditorMode::Replace);
+        type_keys(&mut view, "abcdef");
+        assert_eq!(content(&view), [0, 0xAB, 0xCD, 0xEF]);
+        assert_eq!(view.cursor(), 4);
+
+        // Backspace moves back a byte without removing it.
+        press(&mut view, Key::Backspace);
+        type_keys(&mut view, "1");
+        press(&mut view, Key::Esc);
+        assert_eq!(content(&view), [0, 0xAB, 0xCD, 0x1F]);
+        assert_eq!(view.cursor(), 3);
+        assert_eq!(view.mode, EditorMode::Normal);
+
+        // The whole replace session is undone at once.
+        type_keys(&mut view, "u");
+        assert_eq!(content(&view), counting(3));
+    }
+
+    #[test]
+    fn dot_repeats_the_last_change() {
+        let mut view = hex_area(&counting(32));
+        type_keys(&mut view, "2x.");
+        assert_eq!(content(&view)[..2], [4, 5]);
+
+        // A new count replaces the one of the change.
+        type_keys(&mut view, "3.");
+        assert_eq!(content(&view)[..2], [7, 8]);
+        assert_eq!(view.content.len(), 25);
+
+        type_keys(&mut view, "2rAAw.");
+        assert_eq!(content(&view)[..4], [0xAA, 0xAA, 0xAA, 0xAA]);
+        assert_eq!(view.cursor(), 3);
+        type_keys(&mut view, "w3.");
+        assert_eq!(content(&view)[3..8], [0xAA, 0xAA, 0xAA, 0xAA, 14]);
+        assert_eq!(view.cursor(), 6);
+
+        // Each repetition is undone on its own.
+        type_keys(&mut view, "u");
+        assert_eq!(content(&view)[3..8], [0xAA, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn dot_repeats_visual_changes_on_as_many_bytes() {
+        let mut view = hex_area(&counting(32));
+        type_keys(&mut view, "vwwd");
+        assert_eq!(content(&view)[..2], [3, 4]);
+        type_keys(&mut view, ".");
+        assert_eq!(content(&view)[..2], [6, 7]);
+        type_keys(&mut view, "2.");
+        assert_eq!(content(&view)[..2], [12, 13]);
+
+        type_keys(&mut view, "vwr55w.");
+        assert_eq!(content(&view)[..5], [0x55, 0x55, 0x55, 15, 16]);
+    }
+
+    #[test]
+    fn dot_repeats_puts_with_the_register_given() {
+        let mut view = hex_area(&counting(8));
+        type_keys(&mut view, "\"ay2l$bb\"by2l0P");
+        assert_eq!(content(&view)[..4], [6, 7, 0, 1]);
+        type_keys(&mut view, "\"a.");
+        assert_eq!(content(&view)[..6], [6, 0, 1, 7, 0, 1]);
+        type_keys(&mut view, "gg2.");
+        assert_eq!(content(&view)[..6], [0, 1, 0, 1, 6, 0]);
+    }
+
+    #[test]
+    fn macros_replay_recorded_keys() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "qawrFFq");
+        assert_eq!(view.recording, None);
+        assert_eq!(view.macros.get(&'a').map(Vec::len), Some(4));
+        assert_eq!(content(&view)[..3], [0, 0xFF, 2]);
+
+        type_keys(&mut view, "@a");
+        assert_eq!(content(&view)[..4], [0, 0xFF, 0xFF, 3]);
+        type_keys(&mut view, "2@@");
+        assert_eq!(content(&view)[..6], [0, 0xFF, 0xFF, 0xFF, 0xFF, 5]);
+        assert_eq!(view.cursor(), 4);
+
+        // An uppercase name appends to the macro.
+        type_keys(&mut view, "qAxq");
+        assert_eq!(content(&view)[..6], [0, 0xFF, 0xFF, 0xFF, 5, 6]);
+        type_keys(&mut view, "@a");
+        assert_eq!(content(&view)[..6], [0, 0xFF, 0xFF, 0xFF, 5, 7]);
+        assert_eq!(view.content.len(), 38);
+    }
+
+    #[test]
+    fn macros_stop_when_a_command_fails() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "qawrFFq");
+        type_keys(&mut view, "100@a");
+
+        // `w` fails on the last byte, so nothing more is replaced.
+        assert_eq!(content(&view)[0], 0);
+        assert!(content(&view)[1..].iter().all(|&b| b == 0xFF));
+        assert_eq!(view.cursor(), 39);
+        assert!(view.playback.is_empty());
+        assert!(!view.playing);
+    }
+
+    #[test]
+    fn keys_left_to_the_application_are_not_recorded() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "qa/");
+        view.on_event(Event::CtrlChar('g'));
+        type_keys(&mut view, "xq");
+        assert_eq!(view.macros.get(&'a').map(Vec::len), Some(1));
+
+        type_keys(&mut view, "2@a");
+        assert_eq!(content(&view)[..2], [3, 4]);
+    }
+
+    #[test]
+    fn macros_calling_themselves_stop_after_too_many_keys() {
+        let mut view = hex_area(&counting(40));
+        type_keys(&mut view, "qa0@aq");
+        type_keys(&mut view, "@a");
+
+        let message =
+            format!("Macro stopped after {} keys", MAX_PLAYED_EVENTS);
+        assert_eq!(error(&view), Some(&message[..]));
+        assert!(view.playback.is_empty());
+
+        // Keys typed afterwards work as usual.
+        type_keys(&mut view, "w");
+        assert_eq!(view.cursor(), 1);
+    }
+
+    #[test]
+    fn word_view_edits_whole_words() {
+        let mut view = hex_area(&counting(16));
+        view.set_word_size(4);
+        type_keys(&mut view, "w");
+        assert_eq!(view.cursor(), 4);
+
+        // Words are little endian unless set otherwise.
+        type_keys(&mut view, "r11223344");
+        assert_eq!(content(&view)[4..8], [0x44, 0x33, 0x22, 0x11]);
+        view.set_endian(Endian::Big);
+        type_keys(&mut view, "wr11223344");
+        assert_eq!(content(&view)[8..12], [0x11, 0x22, 0x33, 0x44]);
+
+        // Typing in insert mode inserts a whole word, zeros until typed.
+        type_keys(&mut view, "gg");
+        type_keys(&mut view, "iAB");
+        assert_eq!(content(&view)[..4], [0xAB, 0, 0, 0]);
+        assert_eq!(view.cursor(), 0);
+        type_keys(&mut view, "CDEF01");
+        press(&mut view, Key::Esc);
+        assert_eq!(content(&view)[..5], [0xAB, 0xCD, 0xEF, 0x01, 0]);
+        assert_eq!(view.cursor(), 0);
+        assert_eq!(view.content.len(), 20);
+    }
+
+    #[test]
+    fn a_appends_after_the_whole_word_in_word_view() {
+        let mut view = hex_area(&counting(8));
+        view.set_word_size(4);
+        // From any digit of the word
+        type_keys(&mut view, "lllaFFFFFFFF");
+        press(&mut view, Key::Esc);
+        let expected = [0, 1, 2, 3, 0xFF, 0xFF, 0xFF, 0xFF, 4, 5, 6, 7];
+        assert_eq!(content(&view), expected);
+        assert_eq!(view.cursor(), 4);
+    }
+
+    #[test]
+    fn x_and_byte_motions_delete_whole_words_in_word_view() {
+        let mut view = hex_area(&counting(32));
+        view.set_word_size(4);
+        type_keys(&mut view, "wllx");
+        assert_eq!(content(&view)[..8], [0, 1, 2, 3, 8, 9, 10, 11]);
+        assert_eq!(view.cursor(), 4);
+
+        type_keys(&mut view, "X");
+        assert_eq!(content(&view)[..4], [8, 9, 10, 11]);
+        assert_eq!(view.cursor(), 0);
+
+        type_keys(&mut view, "2x");
+        assert_eq!(content(&view)[..4], [16, 17, 18, 19]);
+        type_keys(&mut view, "wd2h");
+        assert_eq!(content(&view)[..4], [20, 21, 22, 23]);
+        assert_eq!(view.content.len(), 12);
+
+        // The text pane still works on single bytes.
+        press(&mut view, Key::Tab);
+        type_keys(&mut view, "x");
+        assert_eq!(content(&view)[..4], [21, 22, 23, 24]);
+    }
+
+    #[test]
+    fn readonly_mode_refuses_changes() {
+        let mut view = hex_area(&counting(32)).readonly(true);
+        type_keys(&mut view, "yy");
+        for ch in CHANGE_KEYS.chars() {
+            type_keys(&mut view, &format!("3{}", ch));
+            assert_eq!(error(&view), Some("Read-only mode"), "{}", ch);
+            assert_eq!(view.mode, EditorMode::Normal);
+            assert_eq!(view.count, None);
+            assert!(view.pending.is_empty());
+        }
+        view.on_event(Event::CtrlChar('r'));
+        assert_eq!(error(&view), Some("Read-only mode"));
+
+        // Changes are refused in visual mode as well.
+        type_keys(&mut view, "vwd");
+        assert_eq!(error(&view), Some("Read-only mode"));
+        type_keys(&mut view, "rFF");
+        press(&mut view, Key::Esc);
+        assert_eq!(content(&view), counting(32));
+        assert!(!view.is_modified());
+
+        // Anything else works as usual.
+        type_keys(&mut view, "gg2wy2wma`a");
+        assert_eq!(view.registers.get(None), Some(&[2, 3][..]));
+        assert_eq!(view.cursor(), 2);
+        assert_eq!(error(&view), None);
+
+        view.set_readonly(false);
+        type_keys(&mut view, "x");
+        assert_eq!(view.content.len(), 31);
+    }
+
+    #[test]
+    fn ctrl_n_goes_forward_in_the_jump_list() {
+        let mut view = hex_area(&counting(64));
+        type_keys(&mut view, "w3Gw");
+        type_keys(&mut view, "G");
+        assert_eq!(view.cursor(), 63);
+
+        view.on_event(Event::CtrlChar('o'));
+        assert_eq!(view.cursor(), 33);
+        view.on_event(Event::CtrlChar('o'));
+        assert_eq!(view.cursor(), 1);
+        view.on_event(Event::CtrlChar('n'));
+        assert_eq!(view.cursor(), 33);
+        view.on_event(Event::CtrlChar('n'));
+        assert_eq!(view.cursor(), 63);
+
+        // Nothing is left to go forward to.
+        view.on_event(Event::CtrlChar('n'));
+        assert!(view.failed);
+        assert_eq!(view.cursor(), 63);
+    }
+
+    #[test]
+    fn tab_switches_panes() {
+        let mut view = hex_area(&counting(4));
+        type_keys(&mut view, "G");
+        view.on_event(Event::CtrlChar('o'));
+        press(&mut view, Key::Tab);
+        assert_eq!(view.pane, Pane::Text);
+        assert_eq!(view.cursor(), 0);
+
+        // Also while typing.
+        type_keys(&mut view, "iA");
+        press(&mut view, Key::Tab);
+        assert_eq!(view.pane, Pane::Hex);
+        type_keys(&mut view, "42");
+        press(&mut view, Key::Esc);
+        assert_eq!(content(&view), [b'A', 0x42, 0, 1, 2, 3]);
+        press(&mut view, Key::Tab);
+        assert_eq!(view.pane, Pane::Text);
+    }
+
+    #[test]
+    fn big_changes_are_recorded_as_single_edits() {
+        let len = 1 << 20;
+        let mut view = hex_area(&vec![0; len]);
+        type_keys(&mut view, "VGr12");
+        assert!(content(&view).iter().all(|&b| b == 0x12));
+        assert_eq!(view.changes.region_at(len / 2), Some((0, len)));
+        assert_eq!(view.changes.between(0, len).len(), 1);
+
+        // Restoring the saved value of bytes cancels their change.
+        type_keys(&mut view, "ggVr00");
+        assert_eq!(view.changes.region_at(0), None);
+        assert_eq!(view.changes.region_at(16), Some((16, len)));
+
+        type_keys(&mut view, "j");
+        assert!(view.revert_region());
+        assert_eq!(content(&view), vec![0; len]);
+        assert!(view.changes.between(0, len).is_empty());
+
+        let change = view.history.undo().unwrap();
+        assert_eq!(change.edits.len(), 1);
+    }
+}
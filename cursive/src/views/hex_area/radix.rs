@@ -0,0 +1,159 @@
+//! Radixes bytes and words can be shown in by `HexArea`.
+//!
+//! Values are `size` bytes long, from a single byte to 64-bit words.
+
+/// How bytes are written in the hex pane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radix {
+    /// Two hex digits per byte, such as `7F`.
+    Hex,
+    /// Eight binary digits per byte, such as `01111111`.
+    Binary,
+    /// Octal digits, such as `177`.
+    Octal,
+    /// Decimal digits, such as `127`.
+    Decimal,
+}
+
+/// Largest value of `size` bytes.
+fn max_value(size: usize) -> u64 {
+    u64::MAX >> (64 - 8 * size)
+}
+
+impl Radix {
+    /// Base digits are written in.
+    pub fn base(self) -> u32 {
+        match self {
+            Radix::Hex => 16,
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+        }
+    }
+
+    /// Number of digits needed to write any value of `size` bytes: 3 for a
+    /// byte in octal or decimal.
+    pub fn digits(self, size: usize) -> usize {
+        match self {
+            Radix::Hex => 2 * size,
+            Radix::Binary => 8 * size,
+            Radix::Octal => (8 * size + 2) / 3,
+            Radix::Decimal => max_value(size).to_string().len(),
+        }
+    }
+
+    /// Writes `value` with exactly `digits(size)` digits.
+    pub fn format(self, value: u64, size: usize) -> String {
+        let width = self.digits(size);
+        match self {
+            Radix::Hex => format!("{:01$X}", value, width),
+            Radix::Binary => format!("{:01$b}", value, width),
+            Radix::Octal => format!("{:01$o}", value, width),
+            Radix::Decimal => format!("{:01$}", value, width),
+        }
+    }
+
+    /// Character of the digit at `index` of `value`, counting from the
+    /// left.
+    pub fn digit_char(self, value: u64, size: usize, index: usize) -> char {
+        self.format(value, size).chars().nth(index).unwrap_or('0')
+    }
+
+    /// Returns `value` with the digit at `index` set to `digit`, counting
+    /// from the left.
+    ///
+    /// When keeping the following digits would not fit in `size` bytes,
+    /// like typing `5` over the `1` of `216` for a byte in decimal, they are
+    /// cleared so that typing the rest of the value works. Returns `None` if
+    /// the result still does not fit, like `300`.
+    pub fn with_digit(
+        self,
+        value: u64,
+        size: usize,
+        index: usize,
+        digit: u32,
+    ) -> Option<u64> {
+        let base = u128::from(self.base());
+        let weight = base.pow((self.digits(size) - 1 - index) as u32);
+        let max = u128::from(max_value(size));
+        let value = u128::from(value);
+        let old = value / weight % base;
+        let new = value - old * weight + u128::from(digit) * weight;
+        let new = if new > max { new - value % weight } else { new };
+
+        if new > max {
+            None
+        } else {
+            Some(new as u64)
+        }
+    }
+
+    /// Parses a value of `size` bytes, such as `255` for a byte in decimal.
+    ///
+    /// Returns `None` if the value does not fit.
+    pub fn parse(self, text: &str, size: usize) -> Option<u64> {
+        u64::from_str_radix(text, self.base())
+            .ok()
+            .filter(|&value| value <= max_value(size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_values() {
+        assert_eq!(Radix::Hex.format(0x7F, 1), "7F");
+        assert_eq!(Radix::Binary.format(5, 1), "00000101");
+        assert_eq!(Radix::Octal.format(0o177, 1), "177");
+        assert_eq!(Radix::Decimal.format(7, 1), "007");
+        assert_eq!(Radix::Decimal.format(7, 2), "00007");
+        assert_eq!(Radix::Octal.digits(8), 22);
+        assert_eq!(Radix::Decimal.digits(8), 20);
+        assert_eq!(Radix::Decimal.digit_char(216, 1, 1), '1');
+    }
+
+    #[test]
+    fn sets_digits() {
+        assert_eq!(Radix::Hex.with_digit(0x7F, 1, 0, 0xA), Some(0xAF));
+        assert_eq!(Radix::Binary.with_digit(0, 1, 7, 1), Some(1));
+        assert_eq!(Radix::Decimal.with_digit(16, 1, 0, 2), Some(216));
+        assert_eq!(
+            Radix::Hex.with_digit(0, 8, 0, 0xF),
+            Some(0xF000_0000_0000_0000)
+        );
+    }
+
+    #[test]
+    fn clears_following_digits_on_overflow() {
+        // Typing `255` over `216`
+        assert_eq!(Radix::Decimal.with_digit(216, 1, 1, 5), Some(250));
+        assert_eq!(Radix::Decimal.with_digit(250, 1, 2, 5), Some(255));
+        // Typing `2` over the hundreds of `099`
+        assert_eq!(Radix::Decimal.with_digit(99, 1, 0, 2), Some(200));
+        // Octal bytes only take 0 to 3 as their first digit
+        assert_eq!(Radix::Octal.with_digit(0o77, 1, 0, 3), Some(0o377));
+        assert_eq!(
+            Radix::Decimal.with_digit(u64::MAX - 1, 8, 19, 5),
+            Some(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn rejects_digits_which_never_fit() {
+        assert_eq!(Radix::Decimal.with_digit(0, 1, 0, 3), None);
+        assert_eq!(Radix::Octal.with_digit(0, 1, 0, 4), None);
+        assert_eq!(Radix::Decimal.with_digit(0, 2, 0, 7), None);
+        assert_eq!(Radix::Decimal.with_digit(0, 8, 0, 2), None);
+    }
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(Radix::Decimal.parse("255", 1), Some(255));
+        assert_eq!(Radix::Decimal.parse("256", 1), None);
+        assert_eq!(Radix::Binary.parse("101", 1), Some(5));
+        assert_eq!(Radix::Hex.parse("FFFF", 2), Some(0xFFFF));
+        assert_eq!(Radix::Octal.parse("8", 1), None);
+    }
+}
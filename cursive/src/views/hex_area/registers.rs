@@ -0,0 +1,74 @@
+//! Registers holding the bytes yanked and deleted in `HexArea`.
+
+use std::collections::BTreeMap;
+
+/// Name of the register used when none is given.
+pub const UNNAMED: char = '"';
+
+/// Unnamed register, and named registers `a` to `z`.
+///
+/// Like Vim, naming a register in uppercase appends to it instead of
+/// replacing its content.
+#[derive(Debug, Default)]
+pub struct Registers {
+    unnamed: Vec<u8>,
+    named: BTreeMap<char, Vec<u8>>,
+}
+
+impl Registers {
+    /// Creates empty registers.
+    pub fn new() -> Self {
+        Registers::default()
+    }
+
+    /// Returns `true` if `name` can be given after `"`.
+    pub fn is_valid_name(name: char) -> bool {
+        name == UNNAMED || name.is_ascii_alphabetic()
+    }
+
+    /// Stores bytes which were yanked or deleted.
+    ///
+    /// The unnamed register always receives a copy, whether a named one is
+    /// given or not.
+    pub fn store(&mut self, name: Option<char>, bytes: Vec<u8>) {
+        match name {
+            Some(name) if name.is_ascii_uppercase() => {
+                let register =
+                    self.named.entry(name.to_ascii_lowercase()).or_default();
+                register.extend_from_slice(&bytes);
+                self.unnamed = register.clone();
+            }
+            Some(name) if name.is_ascii_lowercase() => {
+                self.named.insert(name, bytes.clone());
+                self.unnamed = bytes;
+            }
+            _ => self.unnamed = bytes,
+        }
+    }
+
+    /// Content of the given register, or of the unnamed one.
+    ///
+    /// Returns `None` if the register is empty.
+    pub fn get(&self, name: Option<char>) -> Option<&[u8]> {
+        let bytes = match name {
+            Some(name) if name.is_ascii_alphabetic() => {
+                self.named.get(&name.to_ascii_lowercase())?
+            }
+            _ => &self.unnamed,
+        };
+
+        if bytes.is_empty() {
+            None
+        } else {
+            Some(bytes)
+        }
+    }
+
+    /// Lists the registers which are not empty, unnamed one first.
+    pub fn iter(&self) -> impl Iterator<Item = (char, &[u8])> {
+        Some((UNNAMED, &self.unnamed[..]))
+            .into_iter()
+            .chain(self.named.iter().map(|(&name, bytes)| (name, &bytes[..])))
+            .filter(|(_, bytes)| !bytes.is_empty())
+    }
+}
@@ -0,0 +1,356 @@
+//! Byte pattern search in `HexArea`.
+
+use super::buffer::Buffer;
+use std::cmp::min;
+use std::ops::Range;
+
+/// Number of positions looked at for each read of the content.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How a search query is turned into bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchMode {
+    /// The query is a sequence of hex bytes, such as `DE AD ?? EF`.
+    Hex,
+    /// The query is text, encoded before being searched for.
+    Text {
+        /// Encoding of the text in the content.
+        encoding: TextEncoding,
+        /// When `true`, ASCII letters match regardless of their case.
+        ignore_case: bool,
+    },
+}
+
+/// Text encodings supported by text searches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// 7-bit ASCII.
+    Ascii,
+    /// UTF-8.
+    Utf8,
+    /// UTF-16, little endian.
+    Utf16Le,
+    /// UTF-16, big endian.
+    Utf16Be,
+}
+
+/// What a single byte of a pattern matches.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ByteMatcher {
+    Any,
+    Exact(u8),
+    /// Used for both cases of a letter.
+    Either(u8, u8),
+}
+
+impl ByteMatcher {
+    fn matches(self, byte: u8) -> bool {
+        match self {
+            ByteMatcher::Any => true,
+            ByteMatcher::Exact(b) => b == byte,
+            ByteMatcher::Either(a, b) => a == byte || b == byte,
+        }
+    }
+}
+
+/// Sequence of bytes to look for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern(Vec<ByteMatcher>);
+
+/// Location of a pattern found in the content.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Match {
+    /// Offset of the first matching byte.
+    pub offset: usize,
+    /// `true` if the search went past one end of the content and continued
+    /// from the other.
+    pub wrapped: bool,
+}
+
+impl Pattern {
+    /// Builds the pattern described by `query`.
+    pub fn new(query: &str, mode: SearchMode) -> Result<Self, String> {
+        match mode {
+            SearchMode::Hex => Pattern::from_hex(query),
+            SearchMode::Text {
+                encoding,
+                ignore_case,
+            } => Pattern::from_text(query, encoding, ignore_case),
+        }
+    }
+
+    /// Parses a sequence of hex bytes, such as `DE AD ?? EF`.
+    ///
+    /// `??` matches any byte. Spaces between bytes are optional.
+    pub fn from_hex(query: &str) -> Result<Self, String> {
+        let digits: Vec<char> =
+            query.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if digits.is_empty() {
+            return Err("Empty search pattern".to_string());
+        }
+        if digits.len() % 2 != 0 {
+            return Err(format!("Incomplete byte in pattern: {}", query));
+        }
+
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        for pair in digits.chunks(2) {
+            let byte = match (pair[0], pair[1]) {
+                ('?', '?') => ByteMatcher::Any,
+                (high, low) => match (high.to_digit(16), low.to_digit(16)) {
+                    (Some(high), Some(low)) => {
+                        ByteMatcher::Exact((high * 16 + low) as u8)
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Invalid hex byte in pattern: {}{}",
+                            high, low
+                        ))
+                    }
+                },
+            };
+            bytes.push(byte);
+        }
+
+        Ok(Pattern(bytes))
+    }
+
+    /// Encodes `query` as text.
+    ///
+    /// Case folding only applies to ASCII letters, whatever the encoding.
+    pub fn from_text(
+        query: &str,
+        encoding: TextEncoding,
+        ignore_case: bool,
+    ) -> Result<Self, String> {
+        if query.is_empty() {
+            return Err("Empty search pattern".to_string());
+        }
+
+        let mut bytes = Vec::new();
+        for ch in query.chars() {
+            // Both cases of a letter have the same encoded length, and only
+            // differ on a single byte.
+            let alternate = if ignore_case && ch.is_ascii_alphabetic() {
+                Some(if ch.is_ascii_lowercase() {
+                    ch.to_ascii_uppercase()
+                } else {
+                    ch.to_ascii_lowercase()
+                })
+            } else {
+                None
+            };
+
+            let encoded = encode_char(ch, encoding)?;
+            let alternate = match alternate {
+                Some(alternate) => encode_char(alternate, encoding)?,
+                None => encoded.clone(),
+            };
+
+            bytes.extend(encoded.into_iter().zip(alternate).map(|(a, b)| {
+                if a == b {
+                    ByteMatcher::Exact(a)
+                } else {
+                    ByteMatcher::Either(a, b)
+                }
+            }));
+        }
+
+        Ok(Pattern(bytes))
+    }
+
+    /// Number of bytes covered by a match.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the pattern matches the content at `offset`.
+    pub fn matches_at(&self, content: &[u8], offset: usize) -> bool {
+        content.len().saturating_sub(offset) >= self.len()
+            && self
+                .0
+                .iter()
+                .zip(&content[offset..])
+                .all(|(expected, byte)| expected.matches(*byte))
+    }
+
+    /// Finds the first match after `from`, or the last one before `from`
+    /// when searching backward.
+    ///
+    /// Wraps around the content if nothing is found before reaching its
+    /// end (or its start).
+    pub fn find(
+        &self,
+        content: &Buffer,
+        from: usize,
+        backward: bool,
+    ) -> Option<Match> {
+        let last = content.len().checked_sub(self.len())?;
+
+        let (first_pass, second_pass) = if backward {
+            (0..min(from, last + 1), from..last + 1)
+        } else {
+            (from + 1..last + 1, 0..min(from, last) + 1)
+        };
+        let found = |range, wrapped| {
+            self.find_in(content, range, backward)
+                .map(|offset| Match { offset, wrapped })
+        };
+        found(first_pass, false).or_else(|| found(second_pass, true))
+    }
+
+    /// Finds the first match starting within `range`, or the last one when
+    /// searching backward.
+    ///
+    /// The content is read a chunk at a time, so that big files are not
+    /// loaded at once.
+    fn find_in(
+        &self,
+        content: &Buffer,
+        range: Range<usize>,
+        backward: bool,
+    ) -> Option<usize> {
+        let mut chunks = range.clone().step_by(CHUNK_SIZE).map(|start| {
+            let end = min(start + CHUNK_SIZE, range.end);
+            (start, end)
+        });
+        let mut search = |(start, end): (usize, usize)| {
+            // Matches starting in the chunk may end after it.
+            let last = min(end + self.len() - 1, content.len());
+            let bytes = content.read_uncached(start, last);
+            let found = |offset: &usize| self.matches_at(&bytes, *offset);
+            let offset = if backward {
+                (0..end - start).rev().find(found)
+            } else {
+                (0..end - start).find(found)
+            };
+            offset.map(|offset| start + offset)
+        };
+
+        if backward {
+            chunks
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .find_map(search)
+        } else {
+            chunks.find_map(&mut search)
+        }
+    }
+}
+
+/// Encodes a single character.
+fn encode_char(ch: char, encoding: TextEncoding) -> Result<Vec<u8>, String> {
+    let mut units = [0; 2];
+
+    Ok(match encoding {
+        TextEncoding::Ascii if !ch.is_ascii() => {
+            return Err(format!("Not an ASCII character: {}", ch))
+        }
+        TextEncoding::Ascii | TextEncoding::Utf8 => {
+            ch.to_string().into_bytes()
+        }
+        TextEncoding::Utf16Le => ch
+            .encode_utf16(&mut units)
+            .iter()
+            .flat_map(|unit| unit.to_le_bytes().to_vec())
+            .collect(),
+        TextEncoding::Utf16Be => ch
+            .encode_utf16(&mut units)
+            .iter()
+            .flat_map(|unit| unit.to_be_bytes().to_vec())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(offset: usize, wrapped: bool) -> Option<Match> {
+        Some(Match { offset, wrapped })
+    }
+
+    #[test]
+    fn parses_hex_patterns() {
+        let pattern = Pattern::from_hex("de AD??ef").unwrap();
+        assert_eq!(pattern.len(), 4);
+        assert!(pattern.matches_at(&[0xDE, 0xAD, 0x00, 0xEF], 0));
+        assert!(!pattern.matches_at(&[0xDE, 0xAD, 0x00, 0xEE], 0));
+        assert!(!pattern.matches_at(&[0xDE, 0xAD, 0x00], 0));
+
+        assert!(Pattern::from_hex("").is_err());
+        assert!(Pattern::from_hex("ABC").is_err());
+        assert!(Pattern::from_hex("G0").is_err());
+    }
+
+    #[test]
+    fn wraps_around_in_both_directions() {
+        let content = Buffer::from(b"ab..ab..ab".to_vec());
+        let pattern = Pattern::from_hex("61 62").unwrap();
+
+        assert_eq!(pattern.find(&content, 0, false), found(4, false));
+        assert_eq!(pattern.find(&content, 8, false), found(0, true));
+        assert_eq!(pattern.find(&content, 8, true), found(4, false));
+        assert_eq!(pattern.find(&content, 0, true), found(8, true));
+    }
+
+    #[test]
+    fn finds_the_match_at_the_cursor_last() {
+        let content = Buffer::from(b"..ab..".to_vec());
+        let pattern = Pattern::from_hex("6162").unwrap();
+
+        assert_eq!(pattern.find(&content, 2, false), found(2, true));
+        assert_eq!(pattern.find(&content, 2, true), found(2, true));
+        assert_eq!(pattern.find(&Buffer::from(b"a".to_vec()), 0, false), None);
+    }
+
+    #[test]
+    fn finds_matches_across_chunks() {
+        let mut bytes = vec![0; 3 * CHUNK_SIZE];
+        bytes[CHUNK_SIZE - 2..CHUNK_SIZE + 2].copy_from_slice(&[1, 2, 3, 4]);
+        bytes[2 * CHUNK_SIZE - 1..2 * CHUNK_SIZE + 1].copy_from_slice(&[1, 2]);
+        let content = Buffer::from(bytes);
+
+        let pattern = Pattern::from_hex("01020304").unwrap();
+        assert_eq!(
+            pattern.find(&content, 0, false),
+            found(CHUNK_SIZE - 2, false)
+        );
+        assert_eq!(
+            pattern.find(&content, 3 * CHUNK_SIZE - 1, true),
+            found(CHUNK_SIZE - 2, false)
+        );
+
+        let pattern = Pattern::from_hex("0102").unwrap();
+        assert_eq!(
+            pattern.find(&content, CHUNK_SIZE, false),
+            found(2 * CHUNK_SIZE - 1, false)
+        );
+        assert_eq!(
+            pattern.find(&content, 3 * CHUNK_SIZE - 1, true),
+            found(2 * CHUNK_SIZE - 1, false)
+        );
+    }
+
+    #[test]
+    fn ignores_case_in_utf16() {
+        let content = Buffer::from(b"\0\0x\0A\0b\0C\0".to_vec());
+        let mode = |ignore_case| SearchMode::Text {
+            encoding: TextEncoding::Utf16Le,
+            ignore_case,
+        };
+
+        let pattern = Pattern::new("aBc", mode(true)).unwrap();
+        assert_eq!(pattern.len(), 6);
+        assert_eq!(pattern.find(&content, 0, false), found(4, false));
+        let pattern = Pattern::new("aBc", mode(false)).unwrap();
+        assert_eq!(pattern.find(&content, 0, false), None);
+
+        let content = Buffer::from(b"\0A\0b".to_vec());
+        let pattern =
+            Pattern::from_text("AB", TextEncoding::Utf16Be, true).unwrap();
+        assert_eq!(pattern.find(&content, 0, false), found(0, true));
+        assert!(Pattern::from_text("é", TextEncoding::Ascii, false).is_err());
+    }
+}
@@ -79,7 +79,9 @@ pub use self::dialog::{Dialog, DialogFocus};
 pub use self::dummy::DummyView;
 pub use self::edit_view::EditView;
 pub use self::enableable_view::EnableableView;
-pub use self::hex_area::HexArea;
+pub use self::hex_area::{
+    Buffer, Endian, HexArea, Radix, SearchMode, TextEncoding,
+};
 pub use self::hideable_view::HideableView;
 pub use self::id_view::{IdView, ViewRef};
 pub use self::layer::Layer;
@@ -0,0 +1,332 @@
+// Evaluation of the addresses typed in the goto prompt and after `:`.
+//
+// An address is an arithmetic expression made of numbers, which are hex
+// unless prefixed with `0n` for decimal, marks such as `'a`, `.` for the cursor
+// and `$` for the last byte. Starting it with `+` or `-` makes it relative to the
+// cursor. With a base address, addresses start there rather than at 0.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// State of the editor which addresses are resolved against.
+pub struct Context {
+    pub cursor: usize,
+    pub len: usize,
+    pub marks: BTreeMap<char, usize>,
+    /// Address of the first byte.
+    pub base: usize,
+}
+
+/// Returns the offset of the byte at `input`, which has to exist.
+pub fn resolve(input: &str, context: &Context) -> Result<usize, String> {
+    let base = context.base as i128;
+    let input = input.trim();
+    let mut parser = Parser {
+        chars: input.chars().peekable(),
+        context,
+    };
+
+    let mut value = parser.expression()?;
+    if let Some(c) = parser.peek() {
+        return Err(format!("Unexpected character in address: {}", c));
+    }
+
+    if input.starts_with('+') || input.starts_with('-') {
+        value = add(base + context.cursor as i128, value)?;
+    }
+
+    if value < base || value >= base + context.len as i128 {
+        let address = if value < 0 {
+            format!("-0x{:X}", value.unsigned_abs())
+        } else {
+            format!("0x{:X}", value)
+        };
+        return Err(format!(
+            "Address out of range: {} (file is {} bytes)",
+            address, context.len
+        ));
+    }
+
+    Ok((value - base) as usize)
+}
+
+fn add(a: i128, b: i128) -> Result<i128, String> {
+    a.checked_add(b)
+        .ok_or_else(|| "Address too large".to_string())
+}
+
+fn subtract(a: i128, b: i128) -> Result<i128, String> {
+    a.checked_sub(b)
+        .ok_or_else(|| "Address too large".to_string())
+}
+
+fn multiply(a: i128, b: i128) -> Result<i128, String> {
+    a.checked_mul(b)
+        .ok_or_else(|| "Address too large".to_string())
+}
+
+fn divide(a: i128, b: i128) -> Result<i128, String> {
+    if b == 0 {
+        return Err("Division by zero in address".to_string());
+    }
+    a.checked_div(b).ok_or_else(out_of_range)
+}
+
+fn remainder(a: i128, b: i128) -> Result<i128, String> {
+    if b == 0 {
+        return Err("Division by zero in address".to_string());
+    }
+    a.checked_rem(b).ok_or_else(out_of_range)
+}
+
+fn negate(a: i128) -> Result<i128, String> {
+    a.checked_neg().ok_or_else(out_of_range)
+}
+
+fn out_of_range() -> String {
+    "Address out of range".to_string()
+}
+
+// Recursive descent parser, computing the value as it goes.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    context: &'a Context,
+}
+
+impl Parser<'_> {
+    // Next character which is not whitespace, without consuming it.
+    fn peek(&mut self) -> Option<char> {
+        while let Some(c) = self.chars.peek() {
+            if !c.is_whitespace() {
+                return Some(*c);
+            }
+            self.chars.next();
+        }
+        None
+    }
+
+    // Sum or difference of terms.
+    fn expression(&mut self) -> Result<i128, String> {
+        let mut value = self.term()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.chars.next();
+                    value = add(value, self.term()?)?;
+                }
+                Some('-') => {
+                    self.chars.next();
+                    value = subtract(value, self.term()?)?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    // Product, quotient or remainder of factors.
+    fn term(&mut self) -> Result<i128, String> {
+        let mut value = self.factor()?;
+        loop {
+            match self.peek() {
+                Some('*') => {
+                    self.chars.next();
+                    value = multiply(value, self.factor()?)?;
+                }
+                Some('/') => {
+                    self.chars.next();
+                    value = divide(value, self.factor()?)?;
+                }
+                Some('%') => {
+                    self.chars.next();
+                    value = remainder(value, self.factor()?)?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<i128, String> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err("Incomplete address".to_string()),
+        };
+
+        if c.is_ascii_hexdigit() {
+            return self.number();
+        }
+
+        self.chars.next();
+        match c {
+            '+' => self.factor(),
+            '-' => negate(self.factor()?),
+            '(' => {
+                let value = self.expression()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.chars.next();
+                        Ok(value)
+                    }
+                    _ => Err("Missing closing parenthesis in address".to_string()),
+                }
+            }
+            '\'' | '`' => {
+                let name = self.chars.next().unwrap_or_default();
+                match self.context.marks.get(&name) {
+                    Some(&offset) => Ok(self.address(offset)),
+                    None => Err(format!("Mark not set: {}", name)),
+                }
+            }
+            '.' => Ok(self.address(self.context.cursor)),
+            '$' => Ok(self.address(self.context.len) - 1),
+            _ => Err(format!("Unexpected character in address: {}", c)),
+        }
+    }
+
+    fn address(&self, offset: usize) -> i128 {
+        self.context.base as i128 + offset as i128
+    }
+
+    fn number(&mut self) -> Result<i128, String> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_alphanumeric() {
+                break;
+            }
+            text.push(c);
+            self.chars.next();
+        }
+
+        parse_number(&text).ok_or_else(|| format!("Invalid number in address: {}", text))
+    }
+}
+
+// Hex number, optionally prefixed with `0x`, or decimal number prefixed with
+// `0n`, as found in addresses.
+pub fn parse_number(text: &str) -> Option<i128> {
+    let prefix = |lower, upper| {
+        text.strip_prefix(lower)
+            .or_else(|| text.strip_prefix(upper))
+    };
+    let parsed = match (prefix("0x", "0X"), prefix("0n", "0N")) {
+        (Some(digits), _) => i128::from_str_radix(digits, 16),
+        (_, Some(digits)) => digits.parse(),
+        _ => i128::from_str_radix(text, 16),
+    };
+    parsed.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        let mut marks = BTreeMap::new();
+        marks.insert('a', 0x100);
+        Context {
+            cursor: 0x40,
+            len: 0x1000,
+            marks,
+            base: 0,
+        }
+    }
+
+    #[test]
+    fn resolves_numbers() {
+        let context = context();
+        assert_eq!(resolve("0x400", &context), Ok(0x400));
+        assert_eq!(resolve("0XFF", &context), Ok(0xFF));
+        assert_eq!(resolve(" 400 ", &context), Ok(0x400));
+        assert_eq!(resolve("ff", &context), Ok(0xFF));
+        assert_eq!(resolve("0n1024", &context), Ok(1024));
+        assert_eq!(resolve("0N16", &context), Ok(16));
+        assert_eq!(resolve("0", &context), Ok(0));
+        assert_eq!(resolve("$", &context), Ok(0xFFF));
+        assert_eq!(resolve(".", &context), Ok(0x40));
+    }
+
+    #[test]
+    fn resolves_expressions() {
+        let context = context();
+        assert_eq!(resolve("0x400 + 3*0x20", &context), Ok(0x460));
+        assert_eq!(resolve("(1 + 1) * 8", &context), Ok(16));
+        assert_eq!(resolve("100 - 10 - 10", &context), Ok(0xE0));
+        assert_eq!(resolve("$ / 2 % 0n16", &context), Ok(0xF));
+        assert_eq!(resolve("0n100 - 0xA", &context), Ok(90));
+        assert_eq!(resolve("'a + 0x20", &context), Ok(0x120));
+        assert_eq!(resolve("`a", &context), Ok(0x100));
+    }
+
+    #[test]
+    fn resolves_relative_addresses() {
+        let context = context();
+        assert_eq!(resolve("+10", &context), Ok(0x50));
+        assert_eq!(resolve("-0x10", &context), Ok(0x30));
+        assert_eq!(resolve("-4 * 2", &context), Ok(0x38));
+        assert_eq!(resolve("+'a", &context), Ok(0x140));
+    }
+
+    #[test]
+    fn resolves_addresses_from_base() {
+        let context = Context {
+            base: 0x0800_0000,
+            ..context()
+        };
+        assert_eq!(resolve("0x08000400", &context), Ok(0x400));
+        assert_eq!(resolve("+10", &context), Ok(0x50));
+        assert_eq!(resolve("'a + 0x20", &context), Ok(0x120));
+        assert_eq!(resolve("$", &context), Ok(0xFFF));
+        assert_eq!(
+            resolve("0x400", &context),
+            Err("Address out of range: 0x400 (file is 4096 bytes)".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let context = context();
+        assert!(resolve("", &context).is_err());
+        assert_eq!(
+            resolve("0n1F0", &context),
+            Err("Invalid number in address: 0n1F0".to_string())
+        );
+        assert_eq!(
+            resolve("12g", &context),
+            Err("Invalid number in address: 12g".to_string())
+        );
+        assert!(resolve("0x", &context).is_err());
+        assert!(resolve("(1 + 2", &context).is_err());
+        assert!(resolve("1 2", &context).is_err());
+        assert!(resolve("4 / 0", &context).is_err());
+        assert!(resolve("'b", &context).is_err());
+        assert_eq!(
+            resolve("0x1000", &context),
+            Err("Address out of range: 0x1000 (file is 4096 bytes)".to_string())
+        );
+        assert_eq!(
+            resolve("-0x41", &context),
+            Err("Address out of range: -0x1 (file is 4096 bytes)".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_addresses_which_overflow() {
+        let context = context();
+        let min = format!("(-0x{:X} - 1)", i128::MAX);
+        let out_of_range = Err("Address out of range".to_string());
+        assert_eq!(resolve(&format!("{} / -1", min), &context), out_of_range);
+        assert_eq!(resolve(&format!("{} % -1", min), &context), out_of_range);
+        assert_eq!(resolve(&format!("-({})", min), &context), out_of_range);
+        assert_eq!(
+            resolve(&format!("{} - 1", min), &context),
+            Err("Address too large".to_string())
+        );
+        assert_eq!(
+            resolve(&min, &context),
+            Err(format!(
+                "Address out of range: -0x{:X} (file is 4096 bytes)",
+                i128::MIN.unsigned_abs()
+            ))
+        );
+    }
+}
@@ -0,0 +1,297 @@
+// Parsing of the Ex commands typed after `:` in the editor.
+
+use cursive::views::{Endian, Radix};
+use std::convert::TryFrom;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `:w [file]`
+    Write(Option<String>),
+    /// `:'<,'>w file`, writing the last visual selection to another file
+    WriteSelection(String),
+    /// `:wq [file]` and `:x [file]`
+    WriteQuit(Option<String>),
+    /// `:q`, or `:q!` when `force` is true
+    Quit { force: bool },
+    /// `:e [file]`, reloading the current file when none is given, or `:e!`
+    /// to discard unsaved changes
+    Edit { path: Option<String>, force: bool },
+    /// `:revert` to restore the run of changed bytes under the cursor, or
+    /// `:revert byte` for that byte alone
+    Revert { byte: bool },
+    /// `:registers`, listing the content of the registers
+    Registers,
+    /// `:marks`, listing the marks which are set
+    Marks,
+    /// `:inspector`, showing or hiding the data inspector
+    Inspector,
+    /// `:<address>`, such as `:0x400`, `:+16` or `:'a + 0x20`
+    Goto(String),
+    /// `:set option=value...`
+    Set(Vec<Setting>),
+}
+
+/// Option changed with `:set`.
+#[derive(Debug, PartialEq)]
+pub enum Setting {
+    /// `cols=N`, or `cols=auto` to fit as many bytes per row as possible
+    Columns(Option<usize>),
+    /// `group=N`, with 0 or 1 meaning no grouping
+    Group(usize),
+    /// `radix=hex`, `bin`, `oct` or `dec`
+    Radix(Radix),
+    /// `word=N`, showing words of 1, 2, 4 or 8 bytes
+    Word(usize),
+    /// `endian=le` or `be`, the byte order of words
+    Endian(Endian),
+    /// `base=N`, the address of the first byte
+    BaseAddress(usize),
+    /// `address=dec`, or `address=hex` for hex addresses
+    DecimalAddresses(bool),
+    /// `readonly`, or `noreadonly` to allow changes again
+    ReadOnly(bool),
+}
+
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+
+    // Typing `:` in visual mode makes the command apply to the selection.
+    if let Some(rest) = input.strip_prefix("'<,'>") {
+        return match parse(rest)? {
+            Command::Write(Some(path)) => Ok(Command::WriteSelection(path)),
+            Command::Write(None) => Err("Writing a selection needs a file name".to_string()),
+            _ => Err(format!("No range allowed: {}", input)),
+        };
+    }
+
+    // Addresses never start like a command name, and may contain spaces. Hex
+    // numbers starting with a letter need `0x` to tell them from commands.
+    if input.starts_with(|c: char| c.is_ascii_digit() || "+-('`.$".contains(c)) {
+        return Ok(Command::Goto(input.to_string()));
+    }
+
+    let (name, arg) = match input.find(char::is_whitespace) {
+        Some(i) => (&input[..i], Some(input[i..].trim().to_string())),
+        None => (input, None),
+    };
+
+    let command = match name {
+        "w" | "write" => Command::Write(arg),
+        "wq" | "x" | "xit" => Command::WriteQuit(arg),
+        "q" | "quit" if arg.is_none() => Command::Quit { force: false },
+        "q!" | "quit!" if arg.is_none() => Command::Quit { force: true },
+        "e" | "edit" => Command::Edit {
+            path: arg,
+            force: false,
+        },
+        "e!" | "edit!" => Command::Edit {
+            path: arg,
+            force: true,
+        },
+        "revert" => match arg.as_deref() {
+            None => Command::Revert { byte: false },
+            Some("byte") => Command::Revert { byte: true },
+            Some(other) => return Err(format!("Invalid argument: {}", other)),
+        },
+        "reg" | "registers" | "di" | "display" if arg.is_none() => Command::Registers,
+        "marks" if arg.is_none() => Command::Marks,
+        "insp" | "inspector" if arg.is_none() => Command::Inspector,
+        "se" | "set" => match arg {
+            Some(arg) => Command::Set(
+                arg.split_whitespace()
+                    .map(parse_setting)
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => return Err("Argument required".to_string()),
+        },
+        "" => return Err("No command given".to_string()),
+        _ => return Err(format!("Not an editor command: {}", input)),
+    };
+
+    Ok(command)
+}
+
+fn parse_setting(input: &str) -> Result<Setting, String> {
+    let (name, value) = match input.find('=') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => (input, ""),
+    };
+    let invalid = || format!("Invalid argument: {}", input);
+
+    match name {
+        "cols" | "columns" => match value {
+            "auto" => Ok(Setting::Columns(None)),
+            _ => match value.parse() {
+                Ok(0) | Err(_) => Err(invalid()),
+                Ok(columns) => Ok(Setting::Columns(Some(columns))),
+            },
+        },
+        "group" => value.parse().map(Setting::Group).map_err(|_| invalid()),
+        "radix" => match value {
+            "hex" => Ok(Setting::Radix(Radix::Hex)),
+            "bin" => Ok(Setting::Radix(Radix::Binary)),
+            "oct" => Ok(Setting::Radix(Radix::Octal)),
+            "dec" => Ok(Setting::Radix(Radix::Decimal)),
+            _ => Err(invalid()),
+        },
+        "word" => match value.parse() {
+            Ok(size @ 1) | Ok(size @ 2) | Ok(size @ 4) | Ok(size @ 8) => Ok(Setting::Word(size)),
+            _ => Err(invalid()),
+        },
+        "endian" => match value {
+            "le" => Ok(Setting::Endian(Endian::Little)),
+            "be" => Ok(Setting::Endian(Endian::Big)),
+            _ => Err(invalid()),
+        },
+        "base" => crate::address::parse_number(value)
+            .and_then(|base| usize::try_from(base).ok())
+            .map(Setting::BaseAddress)
+            .ok_or_else(invalid),
+        "address" => match value {
+            "hex" => Ok(Setting::DecimalAddresses(false)),
+            "dec" => Ok(Setting::DecimalAddresses(true)),
+            _ => Err(invalid()),
+        },
+        "readonly" | "ro" if value.is_empty() => Ok(Setting::ReadOnly(true)),
+        "noreadonly" | "noro" if value.is_empty() => Ok(Setting::ReadOnly(false)),
+        "readonly" | "ro" | "noreadonly" | "noro" => Err(invalid()),
+        _ => Err(format!("Unknown option: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_file_commands() {
+        assert_eq!(parse("w"), Ok(Command::Write(None)));
+        assert_eq!(
+            parse("w  out.bin "),
+            Ok(Command::Write(Some("out.bin".to_string())))
+        );
+        assert_eq!(parse("x"), Ok(Command::WriteQuit(None)));
+        assert_eq!(parse("revert"), Ok(Command::Revert { byte: false }));
+        assert_eq!(parse("reg"), Ok(Command::Registers));
+        assert_eq!(parse("marks"), Ok(Command::Marks));
+        assert_eq!(parse("insp"), Ok(Command::Inspector));
+        assert_eq!(parse("revert byte"), Ok(Command::Revert { byte: true }));
+        assert_eq!(parse("q!"), Ok(Command::Quit { force: true }));
+        assert_eq!(
+            parse("e! fw.img"),
+            Ok(Command::Edit {
+                path: Some("fw.img".to_string()),
+                force: true
+            })
+        );
+    }
+
+    #[test]
+    fn parses_selection_commands() {
+        assert_eq!(
+            parse("'<,'>w part.bin"),
+            Ok(Command::WriteSelection("part.bin".to_string()))
+        );
+        assert_eq!(
+            parse("'<,'>write part.bin"),
+            Ok(Command::WriteSelection("part.bin".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(parse("1F0"), Ok(Command::Goto("1F0".to_string())));
+        assert_eq!(
+            parse("e"),
+            Ok(Command::Edit {
+                path: None,
+                force: false
+            })
+        );
+        assert_eq!(parse("0e"), Ok(Command::Goto("0e".to_string())));
+        assert_eq!(parse("0n1024"), Ok(Command::Goto("0n1024".to_string())));
+        assert_eq!(
+            parse(" 0x400 + 3*0x20"),
+            Ok(Command::Goto("0x400 + 3*0x20".to_string()))
+        );
+        assert_eq!(parse("-16"), Ok(Command::Goto("-16".to_string())));
+        assert_eq!(parse("'a"), Ok(Command::Goto("'a".to_string())));
+        assert_eq!(parse("$"), Ok(Command::Goto("$".to_string())));
+    }
+
+    #[test]
+    fn parses_settings() {
+        assert_eq!(
+            parse("set cols=8 group=4"),
+            Ok(Command::Set(vec![
+                Setting::Columns(Some(8)),
+                Setting::Group(4)
+            ]))
+        );
+        assert_eq!(
+            parse("se columns=auto"),
+            Ok(Command::Set(vec![Setting::Columns(None)]))
+        );
+        assert_eq!(
+            parse("set group=0"),
+            Ok(Command::Set(vec![Setting::Group(0)]))
+        );
+        assert_eq!(
+            parse("set radix=bin"),
+            Ok(Command::Set(vec![Setting::Radix(Radix::Binary)]))
+        );
+        assert_eq!(
+            parse("set word=4 endian=be"),
+            Ok(Command::Set(vec![
+                Setting::Word(4),
+                Setting::Endian(Endian::Big)
+            ]))
+        );
+        assert_eq!(
+            parse("set base=0x08000000 address=dec"),
+            Ok(Command::Set(vec![
+                Setting::BaseAddress(0x0800_0000),
+                Setting::DecimalAddresses(true)
+            ]))
+        );
+        // Like addresses, bare numbers are hex.
+        assert_eq!(
+            parse("set base=4096"),
+            Ok(Command::Set(vec![Setting::BaseAddress(0x4096)]))
+        );
+        assert_eq!(
+            parse("set base=0n4096"),
+            Ok(Command::Set(vec![Setting::BaseAddress(4096)]))
+        );
+        assert_eq!(
+            parse("set ro"),
+            Ok(Command::Set(vec![Setting::ReadOnly(true)]))
+        );
+        assert_eq!(
+            parse("set noreadonly"),
+            Ok(Command::Set(vec![Setting::ReadOnly(false)]))
+        );
+        assert!(parse("set").is_err());
+        assert!(parse("set cols=0").is_err());
+        assert!(parse("set cols").is_err());
+        assert!(parse("set group=-1").is_err());
+        assert!(parse("set wrap").is_err());
+        assert!(parse("set radix=3").is_err());
+        assert!(parse("set word=3").is_err());
+        assert!(parse("set base=0x").is_err());
+        assert!(parse("set base=-1").is_err());
+        assert!(parse("set address=oct").is_err());
+        assert!(parse("set endian=middle").is_err());
+        assert!(parse("set readonly=1").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        assert!(parse("").is_err());
+        assert!(parse("frobnicate").is_err());
+        assert!(parse("q now").is_err());
+        assert!(parse("revert all").is_err());
+        assert!(parse("'<,'>q").is_err());
+        assert!(parse("'<,'>w").is_err());
+    }
+}
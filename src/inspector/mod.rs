@@ -0,0 +1,620 @@
+// Interpretations of the bytes at the cursor, shown and edited through the
+// data inspector.
+
+use cursive::views::Endian;
+use std::fmt::{Display, LowerExp};
+use std::num::{IntErrorKind, ParseIntError};
+
+/// A way of reading the bytes at the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    I8,
+    U8,
+    I16(Endian),
+    U16(Endian),
+    I32(Endian),
+    U32(Endian),
+    I64(Endian),
+    U64(Endian),
+    F32(Endian),
+    F64(Endian),
+    Binary,
+    Octal,
+    Uleb128,
+    Sleb128,
+    /// Seconds since 1970 as a `u32`, like a 32-bit `time_t`.
+    UnixTime(Endian),
+    Utf8,
+}
+
+/// Fields shown by the inspector, in order.
+pub const FIELDS: [Field; 25] = [
+    Field::I8,
+    Field::U8,
+    Field::I16(Endian::Little),
+    Field::I16(Endian::Big),
+    Field::U16(Endian::Little),
+    Field::U16(Endian::Big),
+    Field::I32(Endian::Little),
+    Field::I32(Endian::Big),
+    Field::U32(Endian::Little),
+    Field::U32(Endian::Big),
+    Field::I64(Endian::Little),
+    Field::I64(Endian::Big),
+    Field::U64(Endian::Little),
+    Field::U64(Endian::Big),
+    Field::F32(Endian::Little),
+    Field::F32(Endian::Big),
+    Field::F64(Endian::Little),
+    Field::F64(Endian::Big),
+    Field::Binary,
+    Field::Octal,
+    Field::Uleb128,
+    Field::Sleb128,
+    Field::UnixTime(Endian::Little),
+    Field::UnixTime(Endian::Big),
+    Field::Utf8,
+];
+
+impl Field {
+    pub fn label(self) -> String {
+        let (name, endian) = match self {
+            Field::I8 => ("i8", None),
+            Field::U8 => ("u8", None),
+            Field::I16(endian) => ("i16", Some(endian)),
+            Field::U16(endian) => ("u16", Some(endian)),
+            Field::I32(endian) => ("i32", Some(endian)),
+            Field::U32(endian) => ("u32", Some(endian)),
+            Field::I64(endian) => ("i64", Some(endian)),
+            Field::U64(endian) => ("u64", Some(endian)),
+            Field::F32(endian) => ("f32", Some(endian)),
+            Field::F64(endian) => ("f64", Some(endian)),
+            Field::Binary => ("binary", None),
+            Field::Octal => ("octal", None),
+            Field::Uleb128 => ("ULEB128", None),
+            Field::Sleb128 => ("SLEB128", None),
+            Field::UnixTime(endian) => ("time", Some(endian)),
+            Field::Utf8 => ("UTF-8", None),
+        };
+
+        match endian {
+            Some(Endian::Little) => format!("{} LE", name),
+            Some(Endian::Big) => format!("{} BE", name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Reads the field from the bytes at the cursor.
+    ///
+    /// Returns `None` if there are not enough bytes left.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        let text = match self {
+            Field::I8 => int(bytes, 1, Endian::Little)?.to_string(),
+            Field::U8 => uint(bytes, 1, Endian::Little)?.to_string(),
+            Field::I16(endian) => int(bytes, 2, endian)?.to_string(),
+            Field::U16(endian) => uint(bytes, 2, endian)?.to_string(),
+            Field::I32(endian) => int(bytes, 4, endian)?.to_string(),
+            Field::U32(endian) => uint(bytes, 4, endian)?.to_string(),
+            Field::I64(endian) => int(bytes, 8, endian)?.to_string(),
+            Field::U64(endian) => uint(bytes, 8, endian)?.to_string(),
+            Field::F32(endian) => float(f32::from_bits(uint(bytes, 4, endian)? as u32)),
+            Field::F64(endian) => float(f64::from_bits(uint(bytes, 8, endian)?)),
+            Field::Binary => format!("{:08b}", bytes.first()?),
+            Field::Octal => format!("{:03o}", bytes.first()?),
+            Field::Uleb128 => {
+                let (value, len) = uleb128(bytes)?;
+                with_length(value, len)
+            }
+            Field::Sleb128 => {
+                let (value, len) = sleb128(bytes)?;
+                with_length(value, len)
+            }
+            Field::UnixTime(endian) => date_time(uint(bytes, 4, endian)?),
+            Field::Utf8 => utf8(bytes)?,
+        };
+
+        Some(text)
+    }
+
+    /// Encodes a value typed by the user, in the format `decode` shows it.
+    ///
+    /// Integers may also be typed in hex with a `0x` prefix, and Unix times
+    /// as a number of seconds.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        let name = self.label();
+        let trimmed = text.trim();
+        let bytes = match self {
+            Field::I8 => encode_int(trimmed, &name, 1, Endian::Little, true)?,
+            Field::U8 => encode_int(trimmed, &name, 1, Endian::Little, false)?,
+            Field::I16(endian) => encode_int(trimmed, &name, 2, endian, true)?,
+            Field::U16(endian) => encode_int(trimmed, &name, 2, endian, false)?,
+            Field::I32(endian) => encode_int(trimmed, &name, 4, endian, true)?,
+            Field::U32(endian) => encode_int(trimmed, &name, 4, endian, false)?,
+            Field::I64(endian) => encode_int(trimmed, &name, 8, endian, true)?,
+            Field::U64(endian) => encode_int(trimmed, &name, 8, endian, false)?,
+            Field::F32(endian) => {
+                let value: f32 = parse_float(trimmed, &name)?;
+                endian.write(u64::from(value.to_bits()), 4)
+            }
+            Field::F64(endian) => {
+                let value: f64 = parse_float(trimmed, &name)?;
+                endian.write(value.to_bits(), 8)
+            }
+            Field::Binary => {
+                let digits = trimmed.strip_prefix("0b").unwrap_or(trimmed);
+                let value = u8::from_str_radix(digits, 2)
+                    .map_err(|error| parse_error(&error, trimmed, &name))?;
+                vec![value]
+            }
+            Field::Octal => {
+                let digits = trimmed.strip_prefix("0o").unwrap_or(trimmed);
+                let value = u8::from_str_radix(digits, 8)
+                    .map_err(|error| parse_error(&error, trimmed, &name))?;
+                vec![value]
+            }
+            Field::Uleb128 => {
+                let value = parse_int(trimmed, &name)?;
+                check_range(value, 0, i128::from(u64::MAX), trimmed, &name)?;
+                encode_uleb128(value as u64)
+            }
+            Field::Sleb128 => {
+                let value = parse_int(trimmed, &name)?;
+                check_range(
+                    value,
+                    i128::from(i64::MIN),
+                    i128::from(i64::MAX),
+                    trimmed,
+                    &name,
+                )?;
+                encode_sleb128(value as i64)
+            }
+            Field::UnixTime(endian) => {
+                let value = match parse_date_time(trimmed) {
+                    Some(timestamp) => timestamp,
+                    None => parse_int(trimmed, &name)?,
+                };
+                check_range(value, 0, i128::from(u32::MAX), trimmed, &name)?;
+                endian.write(value as u64, 4)
+            }
+            // Spaces are valid characters, so the text is not trimmed.
+            Field::Utf8 => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c.to_string().into_bytes(),
+                    _ => return Err(invalid(text, &name)),
+                }
+            }
+        };
+
+        Ok(bytes)
+    }
+}
+
+// Unsigned integer made of the first `size` bytes.
+fn uint(bytes: &[u8], size: usize, endian: Endian) -> Option<u64> {
+    Some(endian.read(bytes.get(..size)?))
+}
+
+// Two's complement integer made of the first `size` bytes.
+fn int(bytes: &[u8], size: usize, endian: Endian) -> Option<i64> {
+    let unused = 64 - 8 * size as u32;
+    uint(bytes, size, endian).map(|value| (value << unused) as i64 >> unused)
+}
+
+// Avoids printing dozens of zeros for very small or large numbers.
+fn float<T: Display + LowerExp + Into<f64> + Copy>(value: T) -> String {
+    let magnitude = value.into().abs();
+    if magnitude != 0.0 && !(1e-4..1e16).contains(&magnitude) {
+        format!("{:e}", value)
+    } else {
+        format!("{}", value)
+    }
+}
+
+// Variable-length value, followed by the number of bytes it takes.
+fn with_length<T: Display>(value: T, len: usize) -> String {
+    let unit = if len == 1 { "byte" } else { "bytes" };
+    format!("{} ({} {})", value, len, unit)
+}
+
+// Value and length of an unsigned LEB128 number.
+//
+// Returns `None` if the number is not terminated or does not fit in a u64.
+fn uleb128(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        let low = u64::from(byte & 0x7F);
+        let shift = 7 * i as u32;
+        if shift == 63 && low > 1 {
+            return None;
+        }
+        value |= low << shift;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+// Value and length of a signed LEB128 number.
+fn sleb128(bytes: &[u8]) -> Option<(i64, usize)> {
+    let mut value = 0i64;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        let shift = 7 * i as u32;
+        value |= i64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            // Extend the sign bit of the last group.
+            if shift + 7 < 64 && byte & 0x40 != 0 {
+                value |= -1 << (shift + 7);
+            }
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+// UTC date and time of a Unix timestamp.
+fn date_time(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Howard Hinnant's `civil_from_days`, for days since 1970-01-01.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn invalid(text: &str, name: &str) -> String {
+    format!("Invalid {} value: {}", name, text)
+}
+
+fn out_of_range(text: &str, name: &str) -> String {
+    format!("Value out of range for {}: {}", name, text)
+}
+
+fn parse_error(error: &ParseIntError, text: &str, name: &str) -> String {
+    match error.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => out_of_range(text, name),
+        _ => invalid(text, name),
+    }
+}
+
+fn check_range(value: i128, min: i128, max: i128, text: &str, name: &str) -> Result<(), String> {
+    if value < min || value > max {
+        return Err(format!(
+            "{} (from {} to {})",
+            out_of_range(text, name),
+            min,
+            max
+        ));
+    }
+    Ok(())
+}
+
+// Decimal integer, or hex integer prefixed with `0x`, possibly negative.
+fn parse_int(text: &str, name: &str) -> Result<i128, String> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    // Signs are handled above, and should not be accepted twice.
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(invalid(text, name));
+    }
+
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+    let value = value.map_err(|error| parse_error(&error, text, name))?;
+    Ok(if negative { -value } else { value })
+}
+
+fn encode_int(
+    text: &str,
+    name: &str,
+    size: usize,
+    endian: Endian,
+    signed: bool,
+) -> Result<Vec<u8>, String> {
+    let value = parse_int(text, name)?;
+    let bits = 8 * size as u32;
+    let (min, max) = if signed {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    };
+    check_range(value, min, max, text, name)?;
+
+    // Truncating keeps the two's complement representation.
+    Ok(endian.write(value as u64, size))
+}
+
+fn parse_float<T>(text: &str, name: &str) -> Result<T, String>
+where
+    T: std::str::FromStr + Into<f64> + Copy,
+{
+    let value: T = text.parse().map_err(|_| invalid(text, name))?;
+
+    // Numbers too large for the type are parsed as infinity.
+    let typed_infinity = text.trim_start_matches(['+', '-']);
+    let typed_infinity = ["inf", "infinity"]
+        .iter()
+        .any(|inf| typed_infinity.eq_ignore_ascii_case(inf));
+    if value.into().is_infinite() && !typed_infinity {
+        return Err(out_of_range(text, name));
+    }
+    Ok(value)
+}
+
+fn encode_uleb128(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn encode_sleb128(mut value: i64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        // Done once the remaining bits only repeat the sign bit.
+        let sign = byte & 0x40 != 0;
+        if (value == 0 && !sign) || (value == -1 && sign) {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+// Unix timestamp of a UTC date written as `YYYY-MM-DD [HH:MM[:SS]] [UTC]`.
+fn parse_date_time(text: &str) -> Option<i128> {
+    let text = text.strip_suffix("UTC").unwrap_or(text).trim();
+    let mut parts = text.split_whitespace();
+    let date = parts.next()?;
+    let time = parts.next().unwrap_or("00:00");
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let numbers = |text: &str, separator| -> Option<Vec<i64>> {
+        text.split(separator).map(|n| n.parse().ok()).collect()
+    };
+    let (year, month, day) = match numbers(date, '-')?[..] {
+        [year, month, day] => (year, month, day),
+        _ => return None,
+    };
+    let (hours, minutes, seconds) = match numbers(time, ':')?[..] {
+        [hours, minutes] => (hours, minutes, 0),
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        _ => return None,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_length = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    // Limiting the year to four digits also keeps the arithmetic below from
+    // overflowing.
+    if !(0..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=month_length).contains(&day)
+        || !(0..24).contains(&hours)
+        || !(0..60).contains(&minutes)
+        || !(0..60).contains(&seconds)
+    {
+        return None;
+    }
+
+    // Howard Hinnant's `days_from_civil`, the inverse of `date_time`.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(i128::from(
+        days * 86400 + hours * 3600 + minutes * 60 + seconds,
+    ))
+}
+
+// Character starting at the cursor, with its code point.
+fn utf8(bytes: &[u8]) -> Option<String> {
+    let len = match bytes.first()? {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Some("invalid".to_string()),
+    };
+
+    let text = match std::str::from_utf8(bytes.get(..len)?) {
+        Ok(text) => text,
+        Err(_) => return Some("invalid".to_string()),
+    };
+    let c = text.chars().next()?;
+    Some(format!("{:?} U+{:04X}", c, u32::from(c)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_integers() {
+        let bytes = [0xFE, 0xFF, 0x00, 0x01, 0x02, 0x03, 0x04, 0x85];
+        let decode = |field: Field| field.decode(&bytes);
+        assert_eq!(decode(Field::I8), Some("-2".to_string()));
+        assert_eq!(decode(Field::U8), Some("254".to_string()));
+        assert_eq!(decode(Field::I16(Endian::Little)), Some("-2".to_string()));
+        assert_eq!(decode(Field::U16(Endian::Big)), Some("65279".to_string()));
+        assert_eq!(
+            decode(Field::U32(Endian::Little)),
+            Some("16842750".to_string())
+        );
+        assert_eq!(
+            decode(Field::I64(Endian::Little)),
+            Some("-8861954859616632834".to_string())
+        );
+        assert_eq!(
+            decode(Field::U64(Endian::Big)),
+            Some("18374405009023632517".to_string())
+        );
+        assert_eq!(decode(Field::Binary), Some("11111110".to_string()));
+        assert_eq!(decode(Field::Octal), Some("376".to_string()));
+    }
+
+    #[test]
+    fn decodes_variable_length_values() {
+        let uleb = |bytes: &[u8]| Field::Uleb128.decode(bytes);
+        let sleb = |bytes: &[u8]| Field::Sleb128.decode(bytes);
+        assert_eq!(
+            uleb(&[0xE5, 0x8E, 0x26]),
+            Some("624485 (3 bytes)".to_string())
+        );
+        assert_eq!(
+            sleb(&[0xC0, 0xBB, 0x78]),
+            Some("-123456 (3 bytes)".to_string())
+        );
+        assert_eq!(sleb(&[0x3F]), Some("63 (1 byte)".to_string()));
+        assert_eq!(uleb(&[0x80, 0x80]), None);
+
+        let utf8 = |bytes: &[u8]| Field::Utf8.decode(bytes);
+        assert_eq!(utf8(&[0xC3, 0xA9]), Some("'é' U+00E9".to_string()));
+        assert_eq!(utf8(&[0x0A]), Some("'\\n' U+000A".to_string()));
+        assert_eq!(utf8(&[0xFF]), Some("invalid".to_string()));
+        assert_eq!(utf8(&[0xE2, 0x82]), None);
+    }
+
+    #[test]
+    fn decodes_floats_and_dates() {
+        let decode = |field: Field, bytes: &[u8]| field.decode(bytes);
+        assert_eq!(
+            decode(Field::F32(Endian::Little), &[0x00, 0x00, 0x60, 0x40]),
+            Some("3.5".to_string())
+        );
+        assert_eq!(
+            decode(
+                Field::F64(Endian::Big),
+                &[0x3F, 0xB9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A]
+            ),
+            Some("0.1".to_string())
+        );
+        assert_eq!(
+            decode(Field::F32(Endian::Big), &[0x00, 0x00, 0x00, 0x01]),
+            Some("1e-45".to_string())
+        );
+        assert_eq!(
+            decode(Field::UnixTime(Endian::Big), &[0x65, 0x92, 0x00, 0x80]),
+            Some("2024-01-01 00:00:00 UTC".to_string())
+        );
+        assert_eq!(
+            decode(Field::UnixTime(Endian::Little), &[0xFF, 0xFF, 0xFF, 0xFF]),
+            Some("2106-02-07 06:28:15 UTC".to_string())
+        );
+        assert_eq!(decode(Field::U16(Endian::Little), &[0x01]), None);
+    }
+
+    #[test]
+    fn encodes_values() {
+        let encode = |field: Field, text: &str| field.encode(text);
+        assert_eq!(
+            encode(Field::U32(Endian::Little), "1234"),
+            Ok(vec![0xD2, 0x04, 0, 0])
+        );
+        assert_eq!(encode(Field::I16(Endian::Big), "-2"), Ok(vec![0xFF, 0xFE]));
+        assert_eq!(
+            encode(Field::U16(Endian::Big), " 0xBEEF "),
+            Ok(vec![0xBE, 0xEF])
+        );
+        assert_eq!(
+            encode(Field::F32(Endian::Little), "3.5"),
+            Ok(vec![0, 0, 0x60, 0x40])
+        );
+        assert_eq!(encode(Field::Binary, "0b101"), Ok(vec![5]));
+        assert_eq!(encode(Field::Octal, "377"), Ok(vec![0xFF]));
+        assert_eq!(encode(Field::Uleb128, "624485"), Ok(vec![0xE5, 0x8E, 0x26]));
+        assert_eq!(
+            encode(Field::Sleb128, "-123456"),
+            Ok(vec![0xC0, 0xBB, 0x78])
+        );
+        assert_eq!(encode(Field::Sleb128, "63"), Ok(vec![0x3F]));
+        assert_eq!(encode(Field::Sleb128, "64"), Ok(vec![0xC0, 0x00]));
+        assert_eq!(
+            encode(Field::UnixTime(Endian::Big), "2024-01-01 00:00:00 UTC"),
+            Ok(vec![0x65, 0x92, 0x00, 0x80])
+        );
+        assert_eq!(
+            encode(Field::UnixTime(Endian::Little), "2106-02-07 06:28:15"),
+            Ok(vec![0xFF; 4])
+        );
+        assert_eq!(encode(Field::Utf8, "é"), Ok(vec![0xC3, 0xA9]));
+        assert_eq!(encode(Field::Utf8, " "), Ok(vec![0x20]));
+    }
+
+    #[test]
+    fn rejects_values_out_of_range() {
+        let encode = |field: Field, text: &str| field.encode(text);
+        assert_eq!(
+            encode(Field::U8, "256"),
+            Err("Value out of range for u8: 256 (from 0 to 255)".to_string())
+        );
+        assert!(encode(Field::I8, "-129").is_err());
+        assert!(encode(Field::U32(Endian::Little), "-1").is_err());
+        assert!(encode(Field::U64(Endian::Big), "18446744073709551616").is_err());
+        assert!(encode(
+            Field::I64(Endian::Big),
+            "999999999999999999999999999999999999999999"
+        )
+        .is_err());
+        assert!(encode(Field::F32(Endian::Little), "1e39").is_err());
+        assert!(encode(Field::F32(Endian::Little), "-inf").is_ok());
+        assert!(encode(Field::Binary, "100000000").is_err());
+        assert!(encode(Field::UnixTime(Endian::Little), "2106-02-07 06:28:16").is_err());
+        assert!(encode(Field::UnixTime(Endian::Little), "2023-02-29").is_err());
+        assert!(encode(Field::UnixTime(Endian::Little), "99999999999999-01-01").is_err());
+        assert!(encode(Field::UnixTime(Endian::Little), "9223372036854775807-01-01").is_err());
+        assert_eq!(
+            encode(Field::U16(Endian::Little), "12a"),
+            Err("Invalid u16 LE value: 12a".to_string())
+        );
+        assert!(encode(Field::I32(Endian::Little), "--1").is_err());
+        assert!(encode(Field::Utf8, "ab").is_err());
+    }
+}
@@ -1,26 +1,44 @@
+mod address;
+mod commands;
 mod error_views;
-mod hex_conversion;
+mod inspector;
 mod util;
 
-use hex_conversion::U8_TO_HEX;
+use commands::{Command, Setting};
+use inspector::Field;
 
-use std::collections::HashMap;
 use std::env;
 use std::io;
 
-use cursive::event::Event;
+use cursive::event::{Event, Key};
 use cursive::traits::*;
-use cursive::views::{Dialog, EditView, HexArea, LinearLayout, TextView};
+use cursive::views::{
+    Buffer, Checkbox, Dialog, DummyView, EditView, HexArea, HideableView, LinearLayout,
+    OnEventView, Panel, RadioGroup, ScrollView, SearchMode, SelectView, TextEncoding, TextView,
+    ViewBox,
+};
 use cursive::Cursive;
 
-const HEX_AREA_ID: &'static str = "content";
-const GOTO_ADDRESS_ID: &'static str = "address";
+const MAIN_VIEW_ID: &str = "main";
+const HEX_AREA_ID: &str = "content";
+const GOTO_ADDRESS_ID: &str = "address";
+const SEARCH_QUERY_ID: &str = "query";
+const IGNORE_CASE_ID: &str = "ignore_case";
+const INSPECTOR_ID: &str = "inspector";
+const INSPECTOR_PANEL_ID: &str = "inspector_panel";
+const VALUE_ID: &str = "value";
 
-fn main() -> io::Result<()> {
-    assert_eq!(U8_TO_HEX.len(), 256);
+// Width of the data inspector, enough for the longest values it shows.
+const INSPECTOR_WIDTH: usize = 44;
+
+// Number of bytes at the cursor the inspector may need, for LEB128 numbers.
+const INSPECTED_LENGTH: usize = 10;
 
-    // Get filename from arguments
-    let args: Vec<String> = env::args().collect();
+fn main() -> io::Result<()> {
+    // Get filename and options from arguments
+    let (options, args): (Vec<String>, Vec<String>) =
+        env::args().partition(|arg| arg == "-R" || arg == "--readonly");
+    let readonly = !options.is_empty();
     if args.len() > 2 {
         eprintln!("For now, this app only takes one argument.");
         return Err(io::Error::new(
@@ -40,35 +58,23 @@ fn main() -> io::Result<()> {
     let mut siv = Cursive::ncurses().expect("Failed to create a Cursive root.");
 
     siv.set_user_data(Data {
-        hex_cache: HashMap::new(),
-        file_path: String::new(),
-    });
-
-    // Initialize hex cache to so that conversions from hex to decimal can be looked
-    // up in constant time. Also store file path.
-    siv.with_user_data(|data: &mut Data| {
-        for (i, hex) in U8_TO_HEX.iter().enumerate() {
-            let index = i as u8;
-            data.hex_cache.insert(hex, index);
-        }
-
-        data.file_path = args[1].to_string();
+        file_path: args[1].to_string(),
+        readonly,
     });
 
-    // Read input file to bytes, then convert to hex
-    let byte_buffer: Vec<u8> = match util::read_as_byte_buffer(&args[1]) {
+    // Open input file, its bytes are read as they are displayed
+    let buffer = match util::read_as_buffer(&args[1]) {
         Ok(b) => b,
         Err(why) => panic!("Couldn't read from file: {:?}", why),
     };
 
-    let hex_values: Vec<&'static str> = byte_buffer
-        .iter()
-        .map(|byte| hex_conversion::convert_to_hex(*byte))
-        .collect();
-
-    main_view(&mut siv, &hex_values);
+    // Files we couldn't save anyway are opened read-only.
+    main_view(&mut siv, buffer, readonly || !util::is_writable(&args[1]));
 
-    siv.add_global_callback(Event::CtrlChar('g'), |s| goto_view(s));
+    siv.add_global_callback(Event::CtrlChar('g'), goto_view);
+    siv.add_global_callback(Event::CtrlChar('w'), focus_inspector);
+    siv.add_global_callback('/', |s| search_view(s, false));
+    siv.add_global_callback('?', |s| search_view(s, true));
 
     siv.run();
 
@@ -76,54 +82,457 @@ fn main() -> io::Result<()> {
 }
 
 struct Data {
-    hex_cache: HashMap<&'static str, u8>,
     file_path: String,
+    // Set by `-R`, opening every file read-only
+    readonly: bool,
 }
 
-fn main_view(siv: &mut Cursive, hex_values: &Vec<&str>) {
-    let edit_area = HexArea::from(hex_values).with_id(HEX_AREA_ID);
+fn main_view(siv: &mut Cursive, buffer: Buffer, readonly: bool) {
+    let edit_area = HexArea::from(buffer)
+        .readonly(readonly)
+        .on_command(run_command)
+        .on_cursor_move(|s, _| update_inspector(s))
+        .with_id(HEX_AREA_ID);
 
-    let dialog = Dialog::around(edit_area)
-        .button("Save", |s| {
-            let edit_area = s
-                .find_id::<HexArea>(HEX_AREA_ID)
-                .expect("Expected edit area to exist");
-
-            let content = edit_area.get_content();
+    let fields = SelectView::new()
+        .with_all(
+            inspector::FIELDS
+                .iter()
+                .map(|&field| (field.label(), field)),
+        )
+        .on_submit(edit_value_view)
+        .with_id(INSPECTOR_ID);
+    // Like Ctrl-W in Vim, going back and forth between the two panels.
+    let fields = OnEventView::new(fields)
+        .on_event(Event::CtrlChar('w'), focus_edit_area)
+        .on_event(Key::Esc, focus_edit_area);
 
-            let user_data = &s.user_data::<Data>().expect("Expected user data to exist");
+    let inspector = Panel::new(ScrollView::new(fields))
+        .title("Inspector")
+        .fixed_width(INSPECTOR_WIDTH);
 
-            let buffer =
-                match hex_conversion::convert_hex_str_to_bytes(content, &user_data.hex_cache) {
-                    Ok(b) => b,
-                    Err(_) => {
-                        error_views::panic(s, "Invalid hex characters present.");
-                        return;
-                    }
-                };
+    let layout = LinearLayout::horizontal().child(edit_area).child(
+        HideableView::new(ViewBox::boxed(inspector))
+            .hidden()
+            .with_id(INSPECTOR_PANEL_ID),
+    );
 
-            if let Err(why) = util::write_bytes_to_file(&user_data.file_path, &buffer) {
+    let dialog = Dialog::around(layout)
+        .button("Save", |s| {
+            if let Err(why) = save(s, None) {
                 let message = format!("Couldn't write to file: {:?}", why);
                 error_views::panic(s, &message);
+                return;
             }
 
             s.add_layer(Dialog::text("File saved!").button("Ok", |s| {
                 s.pop_layer();
             }));
         })
-        .button("Quit", Cursive::quit)
+        .button("Inspector", toggle_inspector)
+        .button("Quit", quit)
+        .with_id(MAIN_VIEW_ID)
         .full_screen();
 
     siv.add_layer(dialog);
+    update_save_button(siv);
+}
+
+// Greys out the Save button while the edit area is read-only.
+fn update_save_button(siv: &mut Cursive) {
+    let readonly = is_readonly(siv);
+    siv.call_on_id(MAIN_VIEW_ID, |view: &mut Dialog| {
+        if let Some(button) = view.buttons_mut().next() {
+            button.set_enabled(!readonly);
+        }
+    });
+}
+
+// Writes the content of the editor to `path`, or to the file being edited if
+// no path is given. Returns the number of bytes written.
+fn save(siv: &mut Cursive, path: Option<&str>) -> io::Result<usize> {
+    let mut edit_area = siv
+        .find_id::<HexArea>(HEX_AREA_ID)
+        .expect("Expected edit area to exist");
+
+    let user_data = siv
+        .user_data::<Data>()
+        .expect("Expected user data to exist");
+
+    // Like Vim, writing a copy elsewhere doesn't count as saving the file.
+    let is_current_file = path.map_or(true, |path| util::is_same_file(path, &user_data.file_path));
+    let path = path.unwrap_or(&user_data.file_path);
+
+    if is_current_file && edit_area.is_readonly() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "read-only mode (:set noreadonly to allow writing)",
+        ));
+    }
+
+    // Saving the file itself only writes the pages which were modified.
+    if is_current_file {
+        edit_area.get_buffer_mut().save(path)?;
+        edit_area.mark_saved();
+    } else {
+        util::write_buffer_to_file(path, edit_area.get_buffer())?;
+    }
+    let written = edit_area.get_buffer().len();
+
+    Ok(written)
+}
+
+fn is_modified(siv: &mut Cursive) -> bool {
+    siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.is_modified())
+        .expect("Expected edit area to exist")
+}
+
+fn is_readonly(siv: &mut Cursive) -> bool {
+    siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.is_readonly())
+        .expect("Expected edit area to exist")
+}
+
+// Quits the application, asking what to do with unsaved changes first.
+fn quit(siv: &mut Cursive) {
+    if !is_modified(siv) {
+        siv.quit();
+        return;
+    }
+
+    let dialog = Dialog::text("Save changes before quitting?")
+        .title("Unsaved changes")
+        .button("Save", |s| {
+            s.pop_layer();
+            match save(s, None) {
+                Ok(_) => s.quit(),
+                Err(why) => {
+                    let message = format!("Couldn't write to file: {}", why);
+                    s.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.set_error(message));
+                }
+            }
+        })
+        .button("Discard", Cursive::quit)
+        .button("Cancel", |s| {
+            s.pop_layer();
+        });
+
+    siv.add_layer(dialog);
+}
+
+fn run_command(siv: &mut Cursive, input: &str) {
+    let result = match commands::parse(input) {
+        Ok(command) => execute(siv, command),
+        Err(message) => Err(message),
+    };
+
+    if let Err(message) = result {
+        siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.set_error(message));
+    }
+
+    // Commands such as `:e` or `:revert` may change the bytes inspected.
+    update_inspector(siv);
+}
+
+fn toggle_inspector(siv: &mut Cursive) {
+    siv.call_on_id(INSPECTOR_PANEL_ID, |view: &mut HideableView<ViewBox>| {
+        view.set_visible(!view.is_visible())
+    });
+    update_inspector(siv);
+}
+
+// Moves the focus to the inspector, showing it if needed.
+fn focus_inspector(siv: &mut Cursive) {
+    let hidden = siv
+        .call_on_id(INSPECTOR_PANEL_ID, |view: &mut HideableView<ViewBox>| {
+            !view.is_visible()
+        })
+        .expect("Expected inspector to exist");
+    if hidden {
+        toggle_inspector(siv);
+    }
+
+    let _ = siv.focus_id(INSPECTOR_ID);
+}
+
+fn focus_edit_area(siv: &mut Cursive) {
+    let _ = siv.focus_id(HEX_AREA_ID);
+}
+
+// Bytes at the cursor, as many as the inspector may need.
+fn inspected_bytes(siv: &mut Cursive) -> Vec<u8> {
+    siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+        let buffer = view.get_buffer();
+        let start = view.cursor().min(buffer.len());
+        let end = buffer.len().min(start + INSPECTED_LENGTH);
+        buffer.read(start, end).into_owned()
+    })
+    .expect("Expected edit area to exist")
+}
+
+fn decode(field: Field, bytes: &[u8]) -> String {
+    field.decode(bytes).unwrap_or_else(|| "-".to_string())
+}
+
+// Shows the bytes at the cursor decoded in every way the inspector knows.
+fn update_inspector(siv: &mut Cursive) {
+    let bytes = inspected_bytes(siv);
+
+    siv.call_on_id(INSPECTOR_ID, |view: &mut SelectView<Field>| {
+        for (i, &field) in inspector::FIELDS.iter().enumerate() {
+            if let Some((label, _)) = view.get_item_mut(i) {
+                *label = format!("{:<10}{}", field.label(), decode(field, &bytes)).into();
+            }
+        }
+    });
+}
+
+// Asks for a new value of a field of the inspector, written at the cursor.
+fn edit_value_view(siv: &mut Cursive, field: &Field) {
+    let field = *field;
+    if is_readonly(siv) {
+        siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+            view.set_error("Read-only mode")
+        });
+        return;
+    }
+
+    let current = decode(field, &inspected_bytes(siv));
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(format!("Current value: {}", current)))
+        .child(TextView::new(format!(
+            "Enter a new {} value:",
+            field.label()
+        )))
+        .child(
+            EditView::new()
+                .on_submit(move |s, text| write_value(s, field, text))
+                .with_id(VALUE_ID),
+        );
+
+    let dialog = Dialog::around(layout)
+        .button("Write", move |s| {
+            let text = s
+                .call_on_id(VALUE_ID, |view: &mut EditView| view.get_content())
+                .expect("Expected edit view to exist");
+            write_value(s, field, &text);
+        })
+        .button("Cancel", |s| {
+            s.pop_layer();
+        });
+
+    siv.add_layer(dialog);
+
+    fn write_value(siv: &mut Cursive, field: Field, text: &str) {
+        siv.pop_layer();
+
+        let result = field.encode(text).and_then(|bytes| {
+            let written = siv
+                .call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+                    view.write_at_cursor(&bytes)
+                })
+                .expect("Expected edit area to exist");
+            if written {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Not enough bytes after the cursor for a {}",
+                    field.label()
+                ))
+            }
+        });
+        if let Err(message) = result {
+            siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.set_error(message));
+        }
+
+        update_inspector(siv);
+    }
+}
+
+fn execute(siv: &mut Cursive, command: Command) -> Result<(), String> {
+    match command {
+        Command::Write(path) => {
+            let written = save(siv, path.as_deref())
+                .map_err(|why| format!("Couldn't write to file: {}", why))?;
+            let message = format!("{} bytes written", written);
+            siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.set_status(message));
+        }
+        Command::WriteSelection(path) => {
+            let mut edit_area = siv
+                .find_id::<HexArea>(HEX_AREA_ID)
+                .expect("Expected edit area to exist");
+            let bytes = edit_area
+                .last_selection()
+                .ok_or_else(|| "No previous selection".to_string())?;
+            let user_data = siv
+                .user_data::<Data>()
+                .expect("Expected user data to exist");
+            util::write_part_to_file(&path, &bytes, &user_data.file_path)
+                .map_err(|why| format!("Couldn't write to file: {}", why))?;
+            let message = format!("{} bytes written", bytes.len());
+            edit_area.set_status(message);
+        }
+        Command::WriteQuit(path) => {
+            save(siv, path.as_deref()).map_err(|why| format!("Couldn't write to file: {}", why))?;
+            siv.quit();
+        }
+        Command::Quit { force: true } => siv.quit(),
+        Command::Quit { force: false } => quit(siv),
+        Command::Edit { path, force } => {
+            if !force && is_modified(siv) {
+                return Err("No write since last change (add ! to override)".to_string());
+            }
+
+            let user_data = siv
+                .user_data::<Data>()
+                .expect("Expected user data to exist");
+            let path = path.unwrap_or_else(|| user_data.file_path.clone());
+            let readonly = user_data.readonly || !util::is_writable(&path);
+
+            let buffer = util::read_as_buffer(&path)
+                .map_err(|why| format!("Couldn't read from file: {}", why))?;
+            let message = if readonly {
+                format!("\"{}\" [readonly] {} bytes", path, buffer.len())
+            } else {
+                format!("\"{}\" {} bytes", path, buffer.len())
+            };
+
+            siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+                view.set_buffer(buffer);
+                view.set_readonly(readonly);
+                view.set_status(message);
+            });
+            siv.with_user_data(|data: &mut Data| data.file_path = path);
+            update_save_button(siv);
+        }
+        Command::Revert { .. } if is_readonly(siv) => {
+            return Err("Read-only mode".to_string());
+        }
+        Command::Revert { byte } => {
+            let reverted = siv
+                .call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+                    if byte {
+                        view.revert_byte()
+                    } else {
+                        view.revert_region()
+                    }
+                })
+                .expect("Expected edit area to exist");
+            if !reverted {
+                return Err("No change under the cursor".to_string());
+            }
+        }
+        Command::Registers => registers_view(siv),
+        Command::Marks => marks_view(siv),
+        Command::Inspector => toggle_inspector(siv),
+        Command::Goto(address) => goto(siv, &address)?,
+        Command::Set(settings) => {
+            siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+                for setting in settings {
+                    match setting {
+                        Setting::Columns(columns) => view.set_columns(columns),
+                        Setting::Group(group) => view.set_group(group),
+                        Setting::Radix(radix) => view.set_radix(radix),
+                        Setting::Word(size) => view.set_word_size(size),
+                        Setting::Endian(endian) => view.set_endian(endian),
+                        Setting::BaseAddress(base) => view.set_base_address(base),
+                        Setting::DecimalAddresses(decimal) => view.set_decimal_addresses(decimal),
+                        Setting::ReadOnly(readonly) => view.set_readonly(readonly),
+                    }
+                }
+            });
+            update_save_button(siv);
+        }
+    }
+
+    Ok(())
+}
+
+// Lists the registers holding bytes, with a preview of their content.
+fn registers_view(siv: &mut Cursive) {
+    let lines: Vec<String> = siv
+        .call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+            view.registers()
+                .map(|(name, bytes)| {
+                    format!("\"{}  {:>6} bytes  {}", name, bytes.len(), preview(bytes))
+                })
+                .collect()
+        })
+        .expect("Expected edit area to exist");
+
+    let text = if lines.is_empty() {
+        "All registers are empty".to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    siv.add_layer(Dialog::text(text).title("Registers").button("Ok", |s| {
+        s.pop_layer();
+    }));
+
+    // First bytes of a register, as hex.
+    fn preview(bytes: &[u8]) -> String {
+        const PREVIEW_LENGTH: usize = 16;
+
+        let mut text: Vec<String> = bytes
+            .iter()
+            .take(PREVIEW_LENGTH)
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        if bytes.len() > PREVIEW_LENGTH {
+            text.push("...".to_string());
+        }
+        text.join(" ")
+    }
+}
+
+// Lists the marks which are set, with their address.
+fn marks_view(siv: &mut Cursive) {
+    let lines: Vec<String> = siv
+        .call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+            view.marks()
+                .map(|(name, offset)| format!("{}  {}", name, view.format_address(offset)))
+                .collect()
+        })
+        .expect("Expected edit area to exist");
+
+    let text = if lines.is_empty() {
+        "No marks set".to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    siv.add_layer(Dialog::text(text).title("Marks").button("Ok", |s| {
+        s.pop_layer();
+    }));
+}
+
+// Moves the cursor to an address such as `400`, `+10` or `'a + 0x20`.
+fn goto(siv: &mut Cursive, input: &str) -> Result<(), String> {
+    let mut edit_area = siv
+        .find_id::<HexArea>(HEX_AREA_ID)
+        .expect("Expected edit area to exist");
+
+    let context = address::Context {
+        cursor: edit_area.cursor(),
+        len: edit_area.get_buffer().len(),
+        marks: edit_area.marks().collect(),
+        base: edit_area.base_address(),
+    };
+    let offset = address::resolve(input, &context)?;
+    edit_area.goto(offset);
+
+    Ok(())
 }
 
 fn goto_view(siv: &mut Cursive) {
     let layout = LinearLayout::vertical()
-        .child(TextView::new("Enter a hexidecimal memory address:"))
+        .child(TextView::new(
+            "Enter an address, such as 400, 0n1024, +10 or 'a + 20:",
+        ))
         .child(
             EditView::new()
                 .on_submit(goto_address)
-                .max_content_width(8)
                 .with_id(GOTO_ADDRESS_ID),
         );
 
@@ -137,7 +546,67 @@ fn goto_view(siv: &mut Cursive) {
     siv.add_layer(dialog);
 
     fn goto_address(siv: &mut Cursive, address: &str) {
-        siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.goto(address));
         siv.pop_layer();
+        if let Err(message) = goto(siv, address) {
+            siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| view.set_error(message));
+        }
+        update_inspector(siv);
+    }
+}
+
+fn search_view(siv: &mut Cursive, backward: bool) {
+    // `None` stands for a hex search, which has no encoding.
+    let mut encoding: RadioGroup<Option<TextEncoding>> = RadioGroup::new();
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new("Enter the bytes or text to search for:"))
+        .child(
+            EditView::new()
+                .on_submit({
+                    let encoding = encoding.clone();
+                    move |s, query| search(s, query, *encoding.selection(), backward)
+                })
+                .with_id(SEARCH_QUERY_ID),
+        )
+        .child(DummyView)
+        .child(encoding.button(None, "Hex bytes (?? matches any byte)"))
+        .child(encoding.button(Some(TextEncoding::Ascii), "ASCII text"))
+        .child(encoding.button(Some(TextEncoding::Utf8), "UTF-8 text"))
+        .child(encoding.button(Some(TextEncoding::Utf16Le), "UTF-16LE text"))
+        .child(encoding.button(Some(TextEncoding::Utf16Be), "UTF-16BE text"))
+        .child(DummyView)
+        .child(
+            LinearLayout::horizontal()
+                .child(Checkbox::new().with_id(IGNORE_CASE_ID))
+                .child(TextView::new(" Ignore case (text only)")),
+        );
+
+    let dialog = Dialog::around(layout).button("Search", move |s| {
+        let query = s
+            .call_on_id(SEARCH_QUERY_ID, |view: &mut EditView| view.get_content())
+            .expect("Expected edit view to exist");
+        search(s, &query, *encoding.selection(), backward);
+    });
+
+    siv.add_layer(dialog);
+
+    fn search(siv: &mut Cursive, query: &str, encoding: Option<TextEncoding>, backward: bool) {
+        let ignore_case = siv
+            .call_on_id(IGNORE_CASE_ID, |view: &mut Checkbox| view.is_checked())
+            .expect("Expected checkbox to exist");
+
+        let mode = match encoding {
+            None => SearchMode::Hex,
+            Some(encoding) => SearchMode::Text {
+                encoding,
+                ignore_case,
+            },
+        };
+
+        siv.pop_layer();
+        siv.call_on_id(HEX_AREA_ID, |view: &mut HexArea| {
+            view.search(query, mode, backward)
+        });
+        update_inspector(siv);
     }
 }
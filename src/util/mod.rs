@@ -1,23 +1,127 @@
-use std::fs::File;
+use cursive::views::Buffer;
+use std::fs::{self, File, OpenOptions};
 use std::io;
 use std::io::prelude::*;
 use std::path::Path;
 
-pub fn read_as_byte_buffer(path_str: &str) -> Result<Vec<u8>, io::Error> {
+pub fn read_as_buffer(path_str: &str) -> Result<Buffer, io::Error> {
+    Buffer::open(Path::new(path_str))
+}
+
+pub fn write_bytes_to_file(path_str: &str, buffer: &[u8]) -> Result<(), io::Error> {
     let path = Path::new(path_str);
-    let mut file = File::open(path)?;
+    let mut file = File::create(path)?;
+
+    file.write_all(buffer)?;
+
+    Ok(())
+}
 
-    let mut buffer: Vec<u8> = Vec::new();
-    file.read_to_end(&mut buffer)?;
+// Writes part of the file being edited, at `current_path`, to another file.
+// Overwriting the file itself would lose the rest of its content.
+pub fn write_part_to_file(
+    path_str: &str,
+    bytes: &[u8],
+    current_path: &str,
+) -> Result<(), io::Error> {
+    if is_same_file(path_str, current_path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "can't write part of the file over itself",
+        ));
+    }
 
-    Ok(buffer)
+    write_bytes_to_file(path_str, bytes)
 }
 
-pub fn write_bytes_to_file(path_str: &str, buffer: &Vec<u8>) -> Result<(), io::Error> {
+pub fn write_buffer_to_file(path_str: &str, buffer: &Buffer) -> Result<(), io::Error> {
     let path = Path::new(path_str);
     let mut file = File::create(path)?;
 
-    file.write_all(buffer)?;
+    buffer.write_to(&mut file)?;
 
     Ok(())
 }
+
+// Returns true if both paths lead to the same file, even when written
+// differently or when one is a hard link to the other.
+pub fn is_same_file(a: &str, b: &str) -> bool {
+    a == b
+        || match (fs::metadata(a), fs::metadata(b)) {
+            (Ok(a), Ok(b)) => is_same_inode(&a, &b),
+            _ => false,
+        }
+        || match (fs::canonicalize(a), fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+}
+
+#[cfg(unix)]
+fn is_same_inode(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_inode(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    false
+}
+
+// Returns true if the file can be opened for writing, without changing it.
+pub fn is_writable(path_str: &str) -> bool {
+    OpenOptions::new().write(true).open(path_str).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("util-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn hard_links_are_the_same_file() {
+        let file = temp_path("file");
+        let link = temp_path("link");
+        let other = temp_path("other");
+        fs::write(&file, b"content").unwrap();
+        fs::write(&other, b"content").unwrap();
+        let _ = fs::remove_file(&link);
+        fs::hard_link(&file, &link).unwrap();
+
+        assert!(is_same_file(&file, &file));
+        assert!(is_same_file(&file, &link));
+        assert!(!is_same_file(&file, &other));
+        assert!(!is_same_file(&file, &temp_path("missing")));
+
+        for path in &[file, link, other] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn parts_are_not_written_over_the_file() {
+        let file = temp_path("whole");
+        let link = temp_path("whole-link");
+        let other = temp_path("part");
+        fs::write(&file, b"content").unwrap();
+        let _ = fs::remove_file(&link);
+        fs::hard_link(&file, &link).unwrap();
+
+        assert!(write_part_to_file(&file, b"con", &file).is_err());
+        assert!(write_part_to_file(&link, b"con", &file).is_err());
+        assert_eq!(fs::read(&file).unwrap(), b"content");
+        write_part_to_file(&other, b"con", &file).unwrap();
+        assert_eq!(fs::read(&other).unwrap(), b"con");
+
+        for path in &[file, link, other] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}